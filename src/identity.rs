@@ -165,7 +165,7 @@ fn discover_ssh_from_env() -> Result<LocalSigner> {
     })
 }
 
-fn generated_ssh_identity(data_dir: Option<&Path>) -> Result<LocalSigner> {
+pub(crate) fn generated_ssh_identity(data_dir: Option<&Path>) -> Result<LocalSigner> {
     let base_dir = data_dir
         .map(Path::to_path_buf)
         .unwrap_or_else(|| std::env::temp_dir().join("buddies"));
@@ -1,14 +1,19 @@
 mod identity;
 mod memory;
 mod node;
+mod policy;
 mod protocol;
 mod room;
+mod search;
 mod server;
 mod skill;
+mod skill_md;
 mod storage;
+mod sync;
+mod task;
 mod ticket;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -21,6 +26,9 @@ use rmcp::transport::streamable_http_server::{
 use crate::identity::discover_startup_identity;
 use crate::node::{BuddiesNode, BuddiesNodeConfig};
 use crate::server::BuddiesServer;
+use crate::skill::SkillSearchFilters;
+use crate::skill_md::ImportDefaults;
+use crate::storage::Storage;
 
 fn default_data_dir() -> PathBuf {
     dirs::data_local_dir()
@@ -28,6 +36,87 @@ fn default_data_dir() -> PathBuf {
         .unwrap_or_else(|| PathBuf::from(".buddies"))
 }
 
+const MAINTENANCE_COMMANDS: &[&str] = &["rebuild-indexes", "import-skills", "export-skills"];
+
+fn run_maintenance(command: &str, args: &[String], data_dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(data_dir)?;
+    let storage = Storage::open(&data_dir.join("buddies.redb"))?;
+    match command {
+        "rebuild-indexes" => {
+            storage.rebuild_indexes()?;
+            eprintln!("rebuilt indexes in {}", data_dir.join("buddies.redb").display());
+        }
+        "import-skills" => {
+            let (path, room) = path_and_room(args, "buddies import-skills <path> [--room <room>]")?;
+            let signer = discover_startup_identity(Some(data_dir))?;
+            // Skills published by a running node name its endpoint as their
+            // author. There is no node here, so the signing identity stands
+            // in for it.
+            let author = signer
+                .as_ref()
+                .map(|s| s.identity().to_label())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "import-skills needs a signing identity to name the author; without one, use the import_skills tool of a running server"
+                    )
+                })?;
+            let defaults = ImportDefaults {
+                author: &author,
+                room: room.as_deref(),
+                timestamp: room::now_secs(),
+            };
+            let results = skill_md::import_into(&storage, &path, &defaults, |entry| {
+                if let Some(signer) = signer.as_ref()
+                    && let Err(e) = entry.sign_with(signer)
+                {
+                    eprintln!("failed to sign {}: {e:#}", entry.title);
+                }
+            })?;
+            for (path, result) in results {
+                match result {
+                    Ok(entry) => println!("imported {} {}", entry.hash, path.display()),
+                    Err(e) => eprintln!("skipped {}: {e:#}", path.display()),
+                }
+            }
+        }
+        "export-skills" => {
+            let (dir, room) = path_and_room(args, "buddies export-skills <dir> [--room <room>]")?;
+            let filters = SkillSearchFilters {
+                room,
+                ..Default::default()
+            };
+            let entries: Vec<_> = storage
+                .search_skills("", &filters, usize::MAX, room::now_secs())?
+                .into_iter()
+                .map(|r| r.entry)
+                .collect();
+            for path in skill_md::export(&entries, &dir)? {
+                println!("exported {}", path.display());
+            }
+        }
+        other => anyhow::bail!("unknown command '{other}'"),
+    }
+    Ok(())
+}
+
+/// Parse `<path> [--room <room>]`.
+fn path_and_room(args: &[String], usage: &str) -> Result<(PathBuf, Option<String>)> {
+    match args {
+        [path] => Ok((PathBuf::from(path), None)),
+        [path, flag, room] if flag == "--room" => Ok((PathBuf::from(path), Some(room.clone()))),
+        _ => anyhow::bail!("usage: {usage}"),
+    }
+}
+
+/// Parse a comma-separated capability list, like `linux, docker`.
+fn parse_capabilities(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt()
@@ -42,15 +131,29 @@ async fn main() -> Result<()> {
         .unwrap_or_else(|_| whoami::username().unwrap_or_else(|_| "anonymous".into()));
     let agent_name =
         std::env::var("BUDDIES_AGENT").unwrap_or_else(|_| "unknown-agent".into());
+    let capabilities = std::env::var("BUDDIES_CAPABILITIES")
+        .map(|v| parse_capabilities(&v))
+        .unwrap_or_default();
     let data_path = std::env::var("BUDDIES_DATA_DIR")
         .map(PathBuf::from)
         .ok()
         .or_else(|| Some(default_data_dir()));
 
+    // Maintenance subcommands work on the database directly and exit. A
+    // running server holds the database lock, so stop it first.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = args.first()
+        && MAINTENANCE_COMMANDS.contains(&command.as_str())
+    {
+        let dir = data_path.unwrap_or_else(default_data_dir);
+        return run_maintenance(command, &args[1..], &dir);
+    }
+
     let node = Arc::new(
         BuddiesNode::new(BuddiesNodeConfig {
             user_name,
             agent_name,
+            capabilities,
             signer: discover_startup_identity(data_path.as_deref()).ok().flatten(),
             data_dir: data_path,
         })
@@ -97,3 +200,16 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parse_capabilities;
+
+    #[test]
+    fn capabilities_are_trimmed_lowercased_and_non_empty() {
+        assert_eq!(parse_capabilities(" Linux, DOCKER ,gpu"), vec!["linux", "docker", "gpu"]);
+        assert_eq!(parse_capabilities("linux,, ,docker,"), vec!["linux", "docker"]);
+        assert!(parse_capabilities("").is_empty());
+        assert!(parse_capabilities(" , ").is_empty());
+    }
+}
@@ -2,7 +2,10 @@ use rmcp::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+use crate::identity::verify_signature;
+use crate::protocol::SignerIdentity;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub enum MemoryKind {
     Decision,
     Implementation,
@@ -11,6 +14,17 @@ pub enum MemoryKind {
     Status,
 }
 
+impl MemoryKind {
+    /// Built-in time to live, in seconds, for memories of this kind. Rooms
+    /// can override it; `None` means the memory never expires.
+    pub fn default_ttl(&self) -> Option<u64> {
+        match self {
+            Self::Status => Some(7 * 24 * 60 * 60),
+            Self::Decision | Self::Implementation | Self::Context | Self::Skill => None,
+        }
+    }
+}
+
 impl std::fmt::Display for MemoryKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -49,14 +63,83 @@ pub struct MemoryEntry {
     pub content: String,
     pub tags: Vec<String>,
     pub references: Vec<Uuid>,
+    /// Signer identity of the author. Only this identity may edit or delete
+    /// the memory; memories stored without a signer cannot be changed.
+    pub signed_by: Option<SignerIdentity>,
+    /// Bumped on every edit. Peers keep the highest revision they have seen.
+    pub revision: u32,
+    pub updated_at: u64,
+    /// After this time the memory is hidden from search and purged.
+    pub expires_at: Option<u64>,
+    /// Signature by `signed_by` over [`MemoryEntry::signing_payload`].
+    pub signature: Option<Vec<u8>>,
+}
+
+/// What is left of a deleted memory. Peers keep tombstones so that a peer
+/// still holding the memory, or receiving it late, does not bring it back.
+/// Tombstones are signed by the author and can be relayed by any peer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryTombstone {
+    pub id: Uuid,
+    pub room: String,
+    /// Creation timestamp of the deleted memory, which keeps its sync key.
+    pub timestamp: u64,
+    /// One past the last revision of the memory.
+    pub revision: u32,
+    pub deleted_at: u64,
+    pub signed_by: SignerIdentity,
+    pub signature: Vec<u8>,
+}
+
+impl MemoryTombstone {
+    pub fn signing_payload(&self) -> Vec<u8> {
+        postcard::to_allocvec(&(
+            self.id,
+            &self.room,
+            self.timestamp,
+            self.revision,
+            self.deleted_at,
+            &self.signed_by,
+        ))
+        .expect("MemoryTombstone signing serialization is infallible")
+    }
 }
 
 impl MemoryEntry {
-    pub fn matches_query(&self, query: &str) -> bool {
-        let q = query.to_lowercase();
-        self.title.to_lowercase().contains(&q)
-            || self.content.to_lowercase().contains(&q)
-            || self.tags.iter().any(|t| t.to_lowercase().contains(&q))
+    /// Canonical bytes covered by the memory signature: every field except
+    /// the signature itself.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        postcard::to_allocvec(&(
+            self.id,
+            &self.author,
+            self.timestamp,
+            &self.room,
+            self.kind,
+            &self.title,
+            &self.content,
+            &self.tags,
+            &self.references,
+            &self.signed_by,
+            self.revision,
+            self.updated_at,
+            self.expires_at,
+        ))
+        .expect("MemoryEntry signing serialization is infallible")
+    }
+
+    /// Whether the memory carries a signature by `signed_by` that verifies.
+    /// Shells out to gpg / ssh-keygen.
+    pub fn has_valid_signature(&self) -> bool {
+        match (&self.signed_by, &self.signature) {
+            (Some(identity), Some(signature)) => {
+                verify_signature(identity, &self.signing_payload(), signature).unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
     }
 
     pub fn matches_filters(&self, filters: &SearchFilters) -> bool {
@@ -78,13 +161,171 @@ impl MemoryEntry {
                 return false;
             }
         }
+        if filters.since.is_some_and(|since| self.timestamp < since)
+            || filters.until.is_some_and(|until| self.timestamp > until)
+        {
+            return false;
+        }
+        if let Some(ref author) = filters.author {
+            let by_signer = self
+                .signed_by
+                .as_ref()
+                .is_some_and(|signer| signer.to_label() == *author);
+            if !by_signer && self.author != *author {
+                return false;
+            }
+        }
         true
     }
 }
 
+/// How a memory found by walking reference links relates to the memory it
+/// was reached from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryRelation {
+    /// The memory it was reached from lists it in `references`.
+    References,
+    /// It lists the memory it was reached from in its own `references`.
+    ReferencedBy,
+}
+
+impl std::fmt::Display for MemoryRelation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::References => write!(f, "references"),
+            Self::ReferencedBy => write!(f, "referenced_by"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RelatedMemory {
+    pub entry: MemoryEntry,
+    pub depth: u32,
+    pub relation: MemoryRelation,
+    pub linked_from: Uuid,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct SearchFilters {
     pub room: Option<String>,
     pub kind: Option<String>,
     pub tags: Option<Vec<String>>,
+    /// Only memories created at or after this Unix timestamp.
+    pub since: Option<u64>,
+    /// Only memories created at or before this Unix timestamp.
+    pub until: Option<u64>,
+    /// Only memories by this author: a node id or a signer identity label.
+    pub author: Option<String>,
+}
+
+/// A memory returned by a search, with its BM25 relevance score. The score is
+/// zero when no query terms were given.
+#[derive(Debug, Clone)]
+pub struct MemorySearchResult {
+    pub entry: MemoryEntry,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MemorySort {
+    /// Highest score first, newest first among equal scores.
+    #[default]
+    Relevance,
+    /// Newest first.
+    Recency,
+}
+
+impl std::str::FromStr for MemorySort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "relevance" => Ok(Self::Relevance),
+            "recency" => Ok(Self::Recency),
+            _ => Err(anyhow::anyhow!("unknown sort order: {s} (expected relevance or recency)")),
+        }
+    }
+}
+
+impl MemorySort {
+    pub fn sort(self, results: &mut [MemorySearchResult]) {
+        results.sort_by(|a, b| self.compare(&MemoryCursor::after(a), &MemoryCursor::after(b)));
+    }
+
+    /// Whether `result` comes after `cursor` in this order.
+    pub fn is_after(self, result: &MemorySearchResult, cursor: &MemoryCursor) -> bool {
+        self.compare(&MemoryCursor::after(result), cursor) == std::cmp::Ordering::Greater
+    }
+
+    /// Ties on time break on id, descending, which matches the reverse walk
+    /// over the timeline index used for listing.
+    fn compare(self, a: &MemoryCursor, b: &MemoryCursor) -> std::cmp::Ordering {
+        let by_time = b
+            .timestamp
+            .cmp(&a.timestamp)
+            .then_with(|| b.id.cmp(&a.id));
+        match self {
+            Self::Relevance => b.score.total_cmp(&a.score).then(by_time),
+            Self::Recency => by_time,
+        }
+    }
+}
+
+/// Which slice of an ordered result list to return: at most `limit`
+/// results, starting right after `cursor`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MemoryPage {
+    pub limit: usize,
+    pub cursor: Option<MemoryCursor>,
+}
+
+impl MemoryPage {
+    #[cfg(test)]
+    pub fn first(limit: usize) -> Self {
+        Self {
+            limit,
+            cursor: None,
+        }
+    }
+}
+
+/// Position of the last result of a page; the next page starts right after
+/// it. Tools hand it out as an opaque string.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MemoryCursor {
+    pub score: f64,
+    pub timestamp: u64,
+    pub id: Uuid,
+}
+
+impl MemoryCursor {
+    pub fn after(result: &MemorySearchResult) -> Self {
+        Self {
+            score: result.score,
+            timestamp: result.entry.timestamp,
+            id: result.entry.id,
+        }
+    }
+
+    /// Cursor for a page of listed (unscored) memories.
+    pub fn after_entry(entry: &MemoryEntry) -> Self {
+        Self {
+            score: 0.0,
+            timestamp: entry.timestamp,
+            id: entry.id,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let bytes = postcard::to_allocvec(self).expect("MemoryCursor serialization is infallible");
+        data_encoding::BASE32_NOPAD.encode(&bytes).to_ascii_lowercase()
+    }
+
+    pub fn decode(s: &str) -> anyhow::Result<Self> {
+        let bytes = data_encoding::BASE32_NOPAD
+            .decode(s.to_ascii_uppercase().as_bytes())
+            .map_err(|_| anyhow::anyhow!("invalid cursor"))?;
+        postcard::from_bytes(&bytes).map_err(|_| anyhow::anyhow!("invalid cursor"))
+    }
 }
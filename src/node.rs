@@ -1,25 +1,41 @@
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use iroh::protocol::Router;
 use iroh::Endpoint;
 use iroh_gossip::net::Gossip;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
 
 use crate::identity::LocalSigner;
 use crate::room::RoomManager;
 use crate::storage::Storage;
 
+/// How often joined rooms are re-synced in the background, to catch up on
+/// memories missed while gossip dropped messages.
+const PERIODIC_SYNC_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How often expired memories are purged from storage. Search already hides
+/// them in between.
+const PURGE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// How long finished tasks stay listed before they are purged.
+const TASK_RETENTION_SECS: u64 = 7 * 24 * 60 * 60;
+
 pub struct BuddiesNode {
     pub endpoint: Endpoint,
     pub router: Router,
     pub room_manager: Arc<RoomManager>,
     pub storage: Arc<Storage>,
+    background: CancellationToken,
 }
 
 pub struct BuddiesNodeConfig {
     pub user_name: String,
     pub agent_name: String,
+    pub capabilities: Vec<String>,
     pub data_dir: Option<PathBuf>,
     pub signer: Option<LocalSigner>,
 }
@@ -43,25 +59,91 @@ impl BuddiesNode {
 
         let room_manager = RoomManager::new(
             gossip,
+            endpoint.id(),
             config.user_name,
             config.agent_name,
+            config.capabilities,
             Arc::clone(&storage),
             config.signer,
-        );
+        )?;
+
+        room_manager.recover_tasks().await;
+
+        let rejoin_manager = Arc::clone(&room_manager);
+        tokio::spawn(async move {
+            rejoin_manager.rejoin_saved_rooms().await;
+        });
+
+        // Signature checks shell out to gpg / ssh-keygen, so run them off the
+        // async workers.
+        let reverify_manager = Arc::clone(&room_manager);
+        tokio::task::spawn_blocking(move || match reverify_manager.reverify_skills() {
+            Ok(0) => {}
+            Ok(rejected) => warn!(rejected, "flagged stored skills with invalid signatures"),
+            Err(e) => warn!(error = %e, "failed to re-verify stored skills"),
+        });
+
+        let background = CancellationToken::new();
+        let sync_manager = Arc::clone(&room_manager);
+        let sync_cancel = background.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PERIODIC_SYNC_INTERVAL);
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = sync_cancel.cancelled() => break,
+                    _ = interval.tick() => sync_manager.sync_all_rooms().await,
+                }
+            }
+        });
+
+        let purge_storage = Arc::clone(&storage);
+        let purge_cancel = background.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PURGE_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = purge_cancel.cancelled() => break,
+                    _ = interval.tick() => {
+                        let now = crate::room::now_secs();
+                        match purge_storage.purge_expired(now) {
+                            Ok(0) => {}
+                            Ok(purged) => info!(purged, "purged expired memories"),
+                            Err(e) => warn!(error = %e, "failed to purge expired memories"),
+                        }
+                        if let Err(e) = purge_storage.expire_tasks(now) {
+                            warn!(error = %e, "failed to expire tasks");
+                        }
+                        match purge_storage.purge_finished_tasks(now.saturating_sub(TASK_RETENTION_SECS)) {
+                            Ok(0) => {}
+                            Ok(purged) => info!(purged, "purged finished tasks"),
+                            Err(e) => warn!(error = %e, "failed to purge finished tasks"),
+                        }
+                    }
+                }
+            }
+        });
 
         Ok(Self {
             endpoint,
             router,
             room_manager,
             storage,
+            background,
         })
     }
 
-    pub fn subscribe_task_events(&self) -> tokio::sync::broadcast::Receiver<crate::room::PendingTask> {
+    /// Leave a room and stop rejoining it on startup.
+    pub async fn forget_room(&self, room_name: &str) -> Result<bool> {
+        self.room_manager.forget_room(room_name).await
+    }
+
+    pub fn subscribe_task_events(&self) -> tokio::sync::broadcast::Receiver<crate::room::TaskEvent> {
         self.room_manager.subscribe_task_events()
     }
 
     pub async fn shutdown(&self) -> Result<()> {
+        self.background.cancel();
         self.router.shutdown().await?;
         Ok(())
     }
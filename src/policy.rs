@@ -0,0 +1,383 @@
+use std::collections::{HashMap, HashSet};
+
+use rmcp::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::memory::MemoryKind;
+use crate::protocol::SignerIdentity;
+use crate::skill::{SkillEntry, SkillRanking};
+
+/// Per-room policy enforced on incoming gossip.
+///
+/// Policies are persisted as JSON rather than postcard so that settings added
+/// later can fall back to their defaults when older rows are loaded.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RoomPolicy {
+    /// Signer identities allowed to post in the room. Empty means anyone.
+    #[serde(default)]
+    pub identities: HashSet<SignerIdentity>,
+    /// Drop unsigned messages even when `identities` is empty.
+    #[serde(default)]
+    pub require_signed: bool,
+    /// Drop memories that carry no content signature, wherever they come
+    /// from: gossip, sync, fetches or search responses.
+    #[serde(default)]
+    pub require_signed_memories: bool,
+    /// Drop unsigned task offers, claims, lease grants, progress and
+    /// results even when `identities` is empty, so leases are bound to
+    /// signing identities rather than endpoint ids alone.
+    #[serde(default)]
+    pub require_signed_tasks: bool,
+    /// Opt out of catch-up sync: neither start nor answer memory
+    /// reconciliation rounds in this room.
+    #[serde(default)]
+    pub sync_disabled: bool,
+    /// Per-kind time to live in seconds for memories stored in the room,
+    /// overriding [`MemoryKind::default_ttl`]. `0` means never expire.
+    #[serde(default)]
+    pub memory_ttls: HashMap<MemoryKind, u64>,
+    /// Identities whose skill votes are accepted. Empty means any signed
+    /// identity the room admits.
+    #[serde(default)]
+    pub voters: HashSet<SignerIdentity>,
+    /// Identities that may retract any skill in the room, not just their
+    /// own; for skills whose author has left or never signed them.
+    #[serde(default)]
+    pub admins: HashSet<SignerIdentity>,
+    /// How skill searches started in this room order their results.
+    #[serde(default)]
+    pub ranking: SkillRanking,
+    /// How much a vote counts towards a skill's trusted rank, by voter.
+    #[serde(default)]
+    pub trust_weights: TrustWeights,
+}
+
+/// How far a room trusts a voter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoterTrust {
+    /// On the room's identity whitelist, or the local identity.
+    Whitelisted,
+    /// Endorsed in the room by a whitelisted identity.
+    Endorsed,
+    /// Any other signed identity.
+    Unknown,
+}
+
+impl std::fmt::Display for VoterTrust {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Whitelisted => f.write_str("whitelisted"),
+            Self::Endorsed => f.write_str("endorsed"),
+            Self::Unknown => f.write_str("unknown"),
+        }
+    }
+}
+
+/// Vote weights for a skill's trusted rank. Votes are always signed, so
+/// there is no weight for unsigned voters. The default counts only the
+/// room's own team, and endorsed identities at half weight.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TrustWeights {
+    pub whitelisted: f64,
+    pub endorsed: f64,
+    pub unknown: f64,
+}
+
+impl Default for TrustWeights {
+    fn default() -> Self {
+        Self {
+            whitelisted: 1.0,
+            endorsed: 0.5,
+            unknown: 0.0,
+        }
+    }
+}
+
+impl TrustWeights {
+    pub fn weight(&self, trust: VoterTrust) -> f64 {
+        match trust {
+            VoterTrust::Whitelisted => self.whitelisted,
+            VoterTrust::Endorsed => self.endorsed,
+            VoterTrust::Unknown => self.unknown,
+        }
+    }
+}
+
+/// A whitelisted identity vouching for another identity in a room, so its
+/// votes count towards trusted ranks there. A newer endorsement by the same
+/// endorser replaces an older one; `revoked` withdraws it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityEndorsement {
+    pub room: String,
+    pub endorser: SignerIdentity,
+    pub endorsed: SignerIdentity,
+    pub revoked: bool,
+    pub timestamp: u64,
+    pub signature: Vec<u8>,
+}
+
+impl IdentityEndorsement {
+    /// Canonical byte payload signed by the endorser: everything except the
+    /// signature itself.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        postcard::to_allocvec(&(
+            b"buddies:endorsement",
+            &self.room,
+            &self.endorser,
+            &self.endorsed,
+            self.revoked,
+            self.timestamp,
+        ))
+        .expect("IdentityEndorsement signing serialization is infallible")
+    }
+}
+
+impl RoomPolicy {
+    pub fn identity_labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self.identities.iter().map(|id| id.to_label()).collect();
+        labels.sort();
+        labels
+    }
+
+    pub fn voter_labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self.voters.iter().map(|id| id.to_label()).collect();
+        labels.sort();
+        labels
+    }
+
+    /// Whether the room drops unsigned messages: it has a whitelist or
+    /// requires signatures.
+    pub fn requires_signatures(&self) -> bool {
+        self.require_signed || !self.identities.is_empty()
+    }
+
+    /// Whether delegated tasks in the room must be signed on both sides.
+    pub fn requires_signed_tasks(&self) -> bool {
+        self.require_signed_tasks || self.requires_signatures()
+    }
+
+    pub fn accepts_voter(&self, voter: &SignerIdentity) -> bool {
+        self.voters.is_empty() || self.voters.contains(voter)
+    }
+
+    pub fn admin_labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self.admins.iter().map(|id| id.to_label()).collect();
+        labels.sort();
+        labels
+    }
+
+    /// Whether `identity` may retract `skill`: its own signer always may,
+    /// room admins may retract anything.
+    pub fn may_retract(&self, identity: &SignerIdentity, skill: &SkillEntry) -> bool {
+        skill.signed_by.as_ref() == Some(identity) || self.admins.contains(identity)
+    }
+
+    /// How far the room trusts `voter`, given the identities that currently
+    /// endorse it. Only endorsements by whitelisted identities count;
+    /// `local` is trusted like a whitelisted identity.
+    pub fn voter_trust<'a>(
+        &self,
+        voter: &SignerIdentity,
+        local: Option<&SignerIdentity>,
+        mut endorsers: impl Iterator<Item = &'a SignerIdentity>,
+    ) -> VoterTrust {
+        let trusted = |id: &SignerIdentity| self.identities.contains(id) || local == Some(id);
+        if trusted(voter) {
+            VoterTrust::Whitelisted
+        } else if endorsers.any(trusted) {
+            VoterTrust::Endorsed
+        } else {
+            VoterTrust::Unknown
+        }
+    }
+
+    /// Time to live applied to new memories of `kind` in this room.
+    pub fn memory_ttl(&self, kind: MemoryKind) -> Option<u64> {
+        match self.memory_ttls.get(&kind) {
+            Some(0) => None,
+            Some(ttl) => Some(*ttl),
+            None => kind.default_ttl(),
+        }
+    }
+
+    /// Reset the identity settings, leaving any other room settings in place.
+    pub fn clear_identities(&mut self) {
+        self.identities.clear();
+        self.require_signed = false;
+    }
+}
+
+/// A room's policy together with every endorsement in force there, loaded
+/// once so the trust of many voters can be looked up without going back to
+/// storage for each one.
+pub struct RoomTrust {
+    policy: RoomPolicy,
+    local: Option<SignerIdentity>,
+    /// Endorsed identity -> identities currently endorsing it.
+    endorsers: HashMap<SignerIdentity, Vec<SignerIdentity>>,
+}
+
+impl RoomTrust {
+    /// `endorsements` are the room's stored endorsements; revoked ones are
+    /// skipped.
+    pub fn new(
+        policy: RoomPolicy,
+        local: Option<SignerIdentity>,
+        endorsements: impl IntoIterator<Item = IdentityEndorsement>,
+    ) -> Self {
+        let mut endorsers: HashMap<SignerIdentity, Vec<SignerIdentity>> = HashMap::new();
+        for endorsement in endorsements.into_iter().filter(|e| !e.revoked) {
+            endorsers
+                .entry(endorsement.endorsed)
+                .or_default()
+                .push(endorsement.endorser);
+        }
+        Self {
+            policy,
+            local,
+            endorsers,
+        }
+    }
+
+    pub fn voter_trust(&self, voter: &SignerIdentity) -> VoterTrust {
+        let endorsers = self.endorsers.get(voter).into_iter().flatten();
+        self.policy.voter_trust(voter, self.local.as_ref(), endorsers)
+    }
+
+    /// Weight of a vote by `voter` towards a trusted rank.
+    pub fn weight(&self, voter: &SignerIdentity) -> f64 {
+        self.policy.trust_weights.weight(self.voter_trust(voter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IdentityEndorsement, RoomPolicy, RoomTrust, TrustWeights, VoterTrust};
+    use crate::memory::MemoryKind;
+    use crate::protocol::SignerIdentity;
+    use crate::skill::{SkillEntry, SkillSignatureScheme};
+
+    #[test]
+    fn policy_json_roundtrip_and_missing_fields_default() {
+        let policy = RoomPolicy {
+            identities: [SignerIdentity::Gpg {
+                key_id: "ABC123".into(),
+            }]
+            .into(),
+            require_signed: true,
+            memory_ttls: [(MemoryKind::Status, 0), (MemoryKind::Context, 60)].into(),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_vec(&policy).expect("serialize policy");
+        let decoded: RoomPolicy = serde_json::from_slice(&json).expect("deserialize policy");
+        assert_eq!(decoded, policy);
+
+        assert_eq!(decoded.memory_ttl(MemoryKind::Status), None);
+        assert_eq!(decoded.memory_ttl(MemoryKind::Context), Some(60));
+
+        let legacy: RoomPolicy = serde_json::from_str("{}").expect("deserialize empty policy");
+        assert_eq!(legacy, RoomPolicy::default());
+        assert_eq!(legacy.memory_ttl(MemoryKind::Status), MemoryKind::Status.default_ttl());
+    }
+
+    #[test]
+    fn empty_voter_list_accepts_everyone() {
+        let alice = SignerIdentity::Gpg {
+            key_id: "ALICE".into(),
+        };
+        let bob = SignerIdentity::Gpg {
+            key_id: "BOB".into(),
+        };
+        let mut policy = RoomPolicy::default();
+        assert!(policy.accepts_voter(&bob));
+
+        policy.voters.insert(alice.clone());
+        assert!(policy.accepts_voter(&alice));
+        assert!(!policy.accepts_voter(&bob));
+    }
+
+    #[test]
+    fn only_signer_and_admins_may_retract() {
+        let alice = SignerIdentity::Gpg {
+            key_id: "ALICE".into(),
+        };
+        let bob = SignerIdentity::Gpg {
+            key_id: "BOB".into(),
+        };
+        let skill = SkillEntry {
+            hash: "abc".into(),
+            author: "alice".into(),
+            timestamp: 0,
+            room: "team".into(),
+            title: "deploy".into(),
+            content: String::new(),
+            tags: vec![],
+            version: 1,
+            parent_hash: None,
+            parameters: vec![],
+            signature_scheme: SkillSignatureScheme::V2,
+            signed_by: Some(alice.clone()),
+            signature: None,
+        };
+        let mut policy = RoomPolicy::default();
+        assert!(policy.may_retract(&alice, &skill));
+        assert!(!policy.may_retract(&bob, &skill));
+
+        policy.admins.insert(bob.clone());
+        assert!(policy.may_retract(&bob, &skill));
+    }
+
+    #[test]
+    fn voter_trust_follows_whitelist_and_endorsements() {
+        let alice = SignerIdentity::Gpg {
+            key_id: "ALICE".into(),
+        };
+        let bob = SignerIdentity::Gpg {
+            key_id: "BOB".into(),
+        };
+        let mallory = SignerIdentity::Gpg {
+            key_id: "MALLORY".into(),
+        };
+        let policy = RoomPolicy {
+            identities: [alice.clone()].into(),
+            ..Default::default()
+        };
+        let none = std::iter::empty();
+        assert_eq!(policy.voter_trust(&alice, None, none.clone()), VoterTrust::Whitelisted);
+        assert_eq!(policy.voter_trust(&bob, Some(&bob), none.clone()), VoterTrust::Whitelisted);
+        assert_eq!(policy.voter_trust(&bob, None, [&alice].into_iter()), VoterTrust::Endorsed);
+        assert_eq!(policy.voter_trust(&bob, None, [&mallory].into_iter()), VoterTrust::Unknown);
+        assert_eq!(TrustWeights::default().weight(VoterTrust::Unknown), 0.0);
+    }
+
+    #[test]
+    fn room_trust_counts_only_endorsements_in_force() {
+        let alice = SignerIdentity::Gpg {
+            key_id: "ALICE".into(),
+        };
+        let bob = SignerIdentity::Gpg {
+            key_id: "BOB".into(),
+        };
+        let carol = SignerIdentity::Gpg {
+            key_id: "CAROL".into(),
+        };
+        let endorsement = |endorsed: &SignerIdentity, revoked: bool| IdentityEndorsement {
+            room: "team".into(),
+            endorser: alice.clone(),
+            endorsed: endorsed.clone(),
+            revoked,
+            timestamp: 1,
+            signature: vec![],
+        };
+        let policy = RoomPolicy {
+            identities: [alice.clone()].into(),
+            ..Default::default()
+        };
+        let trust = RoomTrust::new(policy, None, [endorsement(&bob, false), endorsement(&carol, true)]);
+        assert_eq!(trust.voter_trust(&alice), VoterTrust::Whitelisted);
+        assert_eq!(trust.voter_trust(&bob), VoterTrust::Endorsed);
+        assert_eq!(trust.voter_trust(&carol), VoterTrust::Unknown);
+        assert_eq!(trust.weight(&bob), 0.5);
+    }
+}
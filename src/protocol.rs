@@ -3,8 +3,10 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
-use crate::memory::{MemoryEntry, SearchFilters};
-use crate::skill::{SkillEntry, SkillSearchFilters, SkillSearchResult};
+use crate::memory::{MemoryEntry, MemoryPage, MemorySort, MemoryTombstone, SearchFilters};
+use crate::policy::IdentityEndorsement;
+use crate::skill::{SkillEntry, SkillRetraction, SkillSearchFilters, SkillUsage, SkillVote};
+use crate::sync::SyncRange;
 
 pub type TopicId = iroh_gossip::proto::TopicId;
 
@@ -53,7 +55,12 @@ impl SignerIdentity {
 pub enum P2PMessageBody {
     Join {
         name: String,
+        /// Endpoint of the joining peer, so peers can address messages to it.
+        endpoint_id: iroh::EndpointId,
         agent: String,
+        /// Free-form tags for what the peer can do, like `linux` or
+        /// `docker`, matched against `TaskRequest::required_capabilities`.
+        capabilities: Vec<String>,
     },
     Leave {
         name: String,
@@ -61,34 +68,91 @@ pub enum P2PMessageBody {
     MemoryCreated {
         entry: MemoryEntry,
     },
+    /// A new revision of a memory. Only accepted when the message is signed
+    /// by the memory's author.
+    MemoryUpdated {
+        entry: MemoryEntry,
+    },
+    MemoryDeleted {
+        tombstone: MemoryTombstone,
+    },
     StatusUpdate {
         author: String,
         text: String,
     },
+    /// Peers answer with their page of matches in `sort` order. Relevance
+    /// cursors carry the requester's scores, which peers compare against
+    /// their own, so relevance pages from peers are approximate.
     SearchRequest {
         request_id: Uuid,
         query: String,
         filters: SearchFilters,
+        sort: MemorySort,
+        page: MemoryPage,
     },
     SearchResponse {
         request_id: Uuid,
         results: Vec<MemoryEntry>,
         peer_name: String,
     },
+    /// Ask peers for specific memories by id.
+    MemoryFetchRequest {
+        request_id: Uuid,
+        ids: Vec<Uuid>,
+    },
+    MemoryFetchResponse {
+        request_id: Uuid,
+        entries: Vec<MemoryEntry>,
+        peer_name: String,
+    },
     TaskRequest {
         task_id: Uuid,
         source_peer: String,
+        /// The delegator's endpoint; only lease grants from it count.
+        source_endpoint: iroh::EndpointId,
         room: String,
         description: String,
         timeout_secs: u32,
         timestamp: u64,
+        /// Only the peer at this endpoint may run the task.
+        target_peer: Option<iroh::EndpointId>,
+        /// Only peers advertising all of these capabilities may run the task.
+        required_capabilities: Vec<String>,
     },
+    /// A peer asking the delegator for the lease on a task. `claim_id` is
+    /// fresh per claimant; claiming again with the same id renews the lease.
     TaskClaimed {
         task_id: Uuid,
+        claim_id: Uuid,
+        claimed_by: String,
+        claimant_endpoint: iroh::EndpointId,
+    },
+    /// The delegator's answer to claims: the one claim holding the lease on
+    /// a task until `expires_at`, with the endpoint and signer it was claimed
+    /// from. Every other peer drops the task.
+    TaskLeaseGranted {
+        task_id: Uuid,
+        claim_id: Uuid,
         claimed_by: String,
+        claimant_endpoint: iroh::EndpointId,
+        claimant_signer: Option<SignerIdentity>,
+        expires_at: u64,
+        granted_by: iroh::EndpointId,
     },
+    /// Progress on a task from the peer holding its lease, identified by
+    /// its claim and endpoint.
+    TaskProgress {
+        task_id: Uuid,
+        claim_id: Uuid,
+        from: iroh::EndpointId,
+        update: TaskProgressUpdate,
+    },
+    /// The result of a task from the peer holding its lease, identified by
+    /// its claim and endpoint.
     TaskResponse {
         task_id: Uuid,
+        claim_id: Uuid,
+        from: iroh::EndpointId,
         result: TaskResult,
         completed_by: String,
     },
@@ -100,15 +164,73 @@ pub enum P2PMessageBody {
         query: String,
         filters: SkillSearchFilters,
     },
+    /// Matching skills and the votes, usage reports and retractions the
+    /// responder holds for them. Ranks are not sent: the requester merges the
+    /// records and counts them itself, so a vote or report seen by many peers
+    /// still counts once.
     SkillSearchResponse {
         request_id: Uuid,
-        results: Vec<SkillSearchResult>,
+        entries: Vec<SkillEntry>,
+        votes: Vec<SkillVote>,
+        usage: Vec<SkillUsage>,
+        retractions: Vec<SkillRetraction>,
         peer_name: String,
     },
+    /// Ask peers for specific skills by content hash.
+    SkillFetchRequest {
+        request_id: Uuid,
+        hashes: Vec<String>,
+    },
+    SkillFetchResponse {
+        request_id: Uuid,
+        entries: Vec<SkillEntry>,
+        retractions: Vec<SkillRetraction>,
+        peer_name: String,
+    },
+    /// A signed vote. Only accepted when the message is signed by the voter.
     SkillVoteCast {
-        skill_hash: String,
-        voter: String,
-        score: i8,
+        vote: SkillVote,
+    },
+    /// A signed usage report. Only accepted when the message is signed by
+    /// the reporter.
+    SkillUsageReported {
+        usage: SkillUsage,
+    },
+    /// A whitelisted identity endorsing, or revoking its endorsement of,
+    /// another identity. Only accepted when the message is signed by the
+    /// endorser.
+    IdentityEndorsed {
+        endorsement: IdentityEndorsement,
+    },
+    /// Endorsements made by the sender, for a peer that just joined and may
+    /// have missed them. Only `to` processes it, and only endorsements made
+    /// by the message's signer are accepted.
+    EndorsementsShared {
+        to: iroh::EndpointId,
+        endorsements: Vec<IdentityEndorsement>,
+    },
+    /// A skill's signer, or an admin of its room, deprecating or hiding it.
+    /// Only accepted when the message is signed by the retracting identity.
+    SkillRetracted {
+        retraction: SkillRetraction,
+    },
+    /// One round of memory set reconciliation between two endpoints. `to`
+    /// is `None` for the opening round, which every peer in the room answers.
+    SyncRanges {
+        from: iroh::EndpointId,
+        to: Option<iroh::EndpointId>,
+        ranges: Vec<SyncRange>,
+    },
+    SyncWant {
+        from: iroh::EndpointId,
+        to: iroh::EndpointId,
+        ids: Vec<Uuid>,
+    },
+    SyncEntries {
+        from: iroh::EndpointId,
+        to: iroh::EndpointId,
+        entries: Vec<MemoryEntry>,
+        tombstones: Vec<MemoryTombstone>,
     },
 }
 
@@ -118,6 +240,15 @@ pub enum TaskResult {
     Error { message: String },
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskProgressUpdate {
+    /// 0 to 100.
+    pub percent: u8,
+    pub message: String,
+    pub reported_by: String,
+    pub timestamp: u64,
+}
+
 impl P2PMessage {
     pub fn new(body: P2PMessageBody) -> Self {
         Self {
@@ -0,0 +1,187 @@
+//! Tokenization and BM25F scoring for the memory full-text index.
+//!
+//! Each memory is split into three fields (title, content, tags) that are
+//! tokenized separately. Term frequencies are combined with per-field weights
+//! before the usual BM25 saturation, so a hit in the title counts for more
+//! than the same hit buried in the content.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::memory::MemoryEntry;
+
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+const TITLE_WEIGHT: f64 = 2.5;
+const TAGS_WEIGHT: f64 = 2.0;
+const CONTENT_WEIGHT: f64 = 1.0;
+
+/// Query terms at least this long also match indexed terms they prefix, so
+/// `postgres` still finds `postgresql`.
+pub const MIN_PREFIX_LEN: usize = 3;
+/// Discount applied to prefix (non-exact) matches.
+pub const PREFIX_MATCH_WEIGHT: f64 = 0.5;
+
+/// Per-field occurrences of one term in one memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Posting {
+    pub title: u32,
+    pub content: u32,
+    pub tags: u32,
+}
+
+impl Posting {
+    fn weighted(&self) -> f64 {
+        TITLE_WEIGHT * self.title as f64
+            + CONTENT_WEIGHT * self.content as f64
+            + TAGS_WEIGHT * self.tags as f64
+    }
+}
+
+/// Per-field token counts of one memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldLengths {
+    pub title: u64,
+    pub content: u64,
+    pub tags: u64,
+}
+
+impl FieldLengths {
+    fn weighted(&self) -> f64 {
+        TITLE_WEIGHT * self.title as f64
+            + CONTENT_WEIGHT * self.content as f64
+            + TAGS_WEIGHT * self.tags as f64
+    }
+}
+
+/// Corpus-wide statistics needed for BM25: the number of indexed memories
+/// and the summed field lengths across all of them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CorpusStats {
+    pub docs: u64,
+    pub lengths: FieldLengths,
+}
+
+impl CorpusStats {
+    fn avg_weighted_len(&self) -> f64 {
+        if self.docs == 0 {
+            return 1.0;
+        }
+        (self.lengths.weighted() / self.docs as f64).max(1.0)
+    }
+}
+
+/// Lowercase alphanumeric runs of at least two characters.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() >= 2)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Distinct query terms, in query order.
+pub fn query_terms(query: &str) -> Vec<String> {
+    let mut terms = tokenize(query);
+    let mut seen = std::collections::HashSet::new();
+    terms.retain(|t| seen.insert(t.clone()));
+    terms
+}
+
+/// Tokenize a memory into its postings and field lengths.
+pub fn index_entry(entry: &MemoryEntry) -> (HashMap<String, Posting>, FieldLengths) {
+    let mut postings: HashMap<String, Posting> = HashMap::new();
+    let mut lengths = FieldLengths::default();
+
+    for token in tokenize(&entry.title) {
+        postings.entry(token).or_default().title += 1;
+        lengths.title += 1;
+    }
+    for token in tokenize(&entry.content) {
+        postings.entry(token).or_default().content += 1;
+        lengths.content += 1;
+    }
+    for tag in &entry.tags {
+        for token in tokenize(tag) {
+            postings.entry(token).or_default().tags += 1;
+            lengths.tags += 1;
+        }
+    }
+
+    (postings, lengths)
+}
+
+/// Whether an indexed term satisfies a query term, and with what weight.
+pub fn term_match_weight(query_term: &str, indexed_term: &str) -> Option<f64> {
+    if indexed_term == query_term {
+        Some(1.0)
+    } else if query_term.chars().count() >= MIN_PREFIX_LEN && indexed_term.starts_with(query_term) {
+        Some(PREFIX_MATCH_WEIGHT)
+    } else {
+        None
+    }
+}
+
+/// BM25F contribution of a single indexed term to a document's score.
+pub fn term_score(
+    posting: &Posting,
+    doc: &FieldLengths,
+    corpus: &CorpusStats,
+    doc_freq: u64,
+) -> f64 {
+    let tf = posting.weighted();
+    if tf == 0.0 {
+        return 0.0;
+    }
+    let n = corpus.docs.max(doc_freq) as f64;
+    let df = doc_freq as f64;
+    let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+    let norm = 1.0 - B + B * doc.weighted() / corpus.avg_weighted_len();
+    idf * tf * (K1 + 1.0) / (tf + K1 * norm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_splits_on_punctuation_and_lowercases() {
+        assert_eq!(
+            tokenize("Use PostgreSQL for the user_table (v2)!"),
+            vec!["use", "postgresql", "for", "the", "user", "table", "v2"]
+        );
+    }
+
+    #[test]
+    fn prefix_matching_requires_minimum_length() {
+        assert_eq!(term_match_weight("postgres", "postgres"), Some(1.0));
+        assert_eq!(term_match_weight("postgres", "postgresql"), Some(PREFIX_MATCH_WEIGHT));
+        assert_eq!(term_match_weight("db", "dbs"), None);
+    }
+
+    #[test]
+    fn title_hits_outscore_content_hits() {
+        let corpus = CorpusStats {
+            docs: 10,
+            lengths: FieldLengths {
+                title: 30,
+                content: 300,
+                tags: 20,
+            },
+        };
+        let doc = FieldLengths {
+            title: 3,
+            content: 30,
+            tags: 2,
+        };
+        let in_title = Posting {
+            title: 1,
+            ..Default::default()
+        };
+        let in_content = Posting {
+            content: 1,
+            ..Default::default()
+        };
+        assert!(term_score(&in_title, &doc, &corpus, 2) > term_score(&in_content, &doc, &corpus, 2));
+    }
+}
